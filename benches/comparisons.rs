@@ -3,13 +3,15 @@
 extern crate test;
 use test::{Bencher, black_box};
 extern crate num_cpus;
-extern crate crossbeam;
-use crossbeam::sync::ArcCell;
+extern crate arc_swap;
+use arc_swap::ArcSwap;
+extern crate left_right;
+use left_right::Absorb;
 extern crate pairlock;
 use pairlock::PairLock;
 
 use std::cell::Cell;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Mutex};
 use std::sync::atomic::AtomicIsize;
 use std::sync::atomic::Ordering::*;
 use std::thread::spawn;
@@ -78,9 +80,9 @@ fn cell_set_single(b: &mut Bencher) {
 }
 
 
-fn arccell(bencher: &mut Bencher,  workload: WorkLoad) {
+fn pairlock(bencher: &mut Bencher,  workload: WorkLoad) {
     let arc = Arc::new(AtomicIsize::new(0));
-    let lock = Arc::new(ArcCell::new(arc.clone()));
+    let lock = Arc::new(PairLock::new(arc.clone(), arc.clone()));
     for _ in 0..workload.other_getters {
         let lock = lock.clone();
         spawn(move|| {
@@ -109,33 +111,33 @@ fn arccell(bencher: &mut Bencher,  workload: WorkLoad) {
     }
     arc.store(-1, Release);
 }
-#[bench]fn arccell_get_single(b: &mut Bencher) {arccell(b, w(Get, Single))}
-#[bench]fn arccell_set_single(b: &mut Bencher) {arccell(b, w(Set, Single))}
-#[bench]fn arccell_get_only(b: &mut Bencher) {arccell(b, w(Get, GetOnly))}
-#[bench]fn arccell_set_only(b: &mut Bencher) {arccell(b, w(Set, SetOnly))}
-#[bench]fn arccell_get_other_set(b: &mut Bencher) {arccell(b, w(Get, SetOnly))}
-#[bench]fn arccell_set_other_get(b: &mut Bencher) {arccell(b, w(Set, GetOnly))}
-#[bench]fn arccell_get_heavy(b: &mut Bencher) {arccell(b, w(Get, GetHeavy))}
-#[bench]fn arccell_set_heavy(b: &mut Bencher) {arccell(b, w(Set, SetHeavy))}
-#[bench]fn arccell_get_light(b: &mut Bencher) {arccell(b, w(Get, SetHeavy))}
-#[bench]fn arccell_set_light(b: &mut Bencher) {arccell(b, w(Set, GetHeavy))}
-#[bench]fn arccell_get_mixed(b: &mut Bencher) {arccell(b, w(Get, Mixed))}
-#[bench]fn arccell_set_mixed(b: &mut Bencher) {arccell(b, w(Set, Mixed))}
+#[bench]fn pairlock_get_single(b: &mut Bencher) {pairlock(b, w(Get, Single))}
+#[bench]fn pairlock_set_single(b: &mut Bencher) {pairlock(b, w(Set, Single))}
+#[bench]fn pairlock_get_only(b: &mut Bencher) {pairlock(b, w(Get, GetOnly))}
+#[bench]fn pairlock_set_only(b: &mut Bencher) {pairlock(b, w(Set, SetOnly))}
+#[bench]fn pairlock_get_other_set(b: &mut Bencher) {pairlock(b, w(Get, SetOnly))}
+#[bench]fn pairlock_set_other_get(b: &mut Bencher) {pairlock(b, w(Set, GetOnly))}
+#[bench]fn pairlock_get_heavy(b: &mut Bencher) {pairlock(b, w(Get, GetHeavy))}
+#[bench]fn pairlock_set_heavy(b: &mut Bencher) {pairlock(b, w(Set, SetHeavy))}
+#[bench]fn pairlock_get_light(b: &mut Bencher) {pairlock(b, w(Get, SetHeavy))}
+#[bench]fn pairlock_set_light(b: &mut Bencher) {pairlock(b, w(Set, GetHeavy))}
+#[bench]fn pairlock_get_mixed(b: &mut Bencher) {pairlock(b, w(Get, Mixed))}
+#[bench]fn pairlock_set_mixed(b: &mut Bencher) {pairlock(b, w(Set, Mixed))}
 #[bench]
-fn arccell_set_single_alloc(b: &mut Bencher) {
-    let lock = ArcCell::new(Arc::new(0));
+fn pairlock_set_single_alloc(b: &mut Bencher) {
+    let lock = PairLock::new(Arc::new(0), Arc::new(0));
     b.iter(|| lock.set(Arc::new(0)) );
 }
 
 
-fn pairlock(bencher: &mut Bencher,  workload: WorkLoad) {
+fn arcswap(bencher: &mut Bencher,  workload: WorkLoad) {
     let arc = Arc::new(AtomicIsize::new(0));
-    let lock = Arc::new(PairLock::new(arc.clone(), arc.clone()));
+    let lock = Arc::new(ArcSwap::new(arc.clone()));
     for _ in 0..workload.other_getters {
         let lock = lock.clone();
         spawn(move|| {
-            lock.get().fetch_add(1, SeqCst);
-            while lock.get().load(Acquire) != -1
+            lock.load_full().fetch_add(1, SeqCst);
+            while lock.load_full().load(Acquire) != -1
                 {}
         });
     }
@@ -145,7 +147,7 @@ fn pairlock(bencher: &mut Bencher,  workload: WorkLoad) {
         spawn(move|| {
             arc.fetch_add(1, SeqCst);
             while arc.load(Acquire) != -1 {
-                lock.set(arc.clone());
+                lock.store(arc.clone());
             }
         });
     }
@@ -153,29 +155,93 @@ fn pairlock(bencher: &mut Bencher,  workload: WorkLoad) {
     while arc.load(Acquire) != workload.other_getters + workload.other_setters
         {}
     match workload.bench {
-        Some(Get) => bencher.iter(|| lock.get() ),
-        Some(Set) => bencher.iter(|| lock.set(arc.clone()) ),
+        Some(Get) => bencher.iter(|| lock.load_full() ),
+        Some(Set) => bencher.iter(|| lock.store(arc.clone()) ),
         None => {}
     }
     arc.store(-1, Release);
 }
-#[bench]fn pairlock_get_single(b: &mut Bencher) {pairlock(b, w(Get, Single))}
-#[bench]fn pairlock_set_single(b: &mut Bencher) {pairlock(b, w(Set, Single))}
-#[bench]fn pairlock_get_only(b: &mut Bencher) {pairlock(b, w(Get, GetOnly))}
-#[bench]fn pairlock_set_only(b: &mut Bencher) {pairlock(b, w(Set, SetOnly))}
-#[bench]fn pairlock_get_other_set(b: &mut Bencher) {pairlock(b, w(Get, SetOnly))}
-#[bench]fn pairlock_set_other_get(b: &mut Bencher) {pairlock(b, w(Set, GetOnly))}
-#[bench]fn pairlock_get_heavy(b: &mut Bencher) {pairlock(b, w(Get, GetHeavy))}
-#[bench]fn pairlock_set_heavy(b: &mut Bencher) {pairlock(b, w(Set, SetHeavy))}
-#[bench]fn pairlock_get_light(b: &mut Bencher) {pairlock(b, w(Get, SetHeavy))}
-#[bench]fn pairlock_set_light(b: &mut Bencher) {pairlock(b, w(Set, GetHeavy))}
-#[bench]fn pairlock_get_mixed(b: &mut Bencher) {pairlock(b, w(Get, Mixed))}
-#[bench]fn pairlock_set_mixed(b: &mut Bencher) {pairlock(b, w(Set, Mixed))}
+#[bench]fn arcswap_get_single(b: &mut Bencher) {arcswap(b, w(Get, Single))}
+#[bench]fn arcswap_set_single(b: &mut Bencher) {arcswap(b, w(Set, Single))}
+#[bench]fn arcswap_get_only(b: &mut Bencher) {arcswap(b, w(Get, GetOnly))}
+#[bench]fn arcswap_set_only(b: &mut Bencher) {arcswap(b, w(Set, SetOnly))}
+#[bench]fn arcswap_get_other_set(b: &mut Bencher) {arcswap(b, w(Get, SetOnly))}
+#[bench]fn arcswap_set_other_get(b: &mut Bencher) {arcswap(b, w(Set, GetOnly))}
+#[bench]fn arcswap_get_heavy(b: &mut Bencher) {arcswap(b, w(Get, GetHeavy))}
+#[bench]fn arcswap_set_heavy(b: &mut Bencher) {arcswap(b, w(Set, SetHeavy))}
+#[bench]fn arcswap_get_light(b: &mut Bencher) {arcswap(b, w(Get, SetHeavy))}
+#[bench]fn arcswap_set_light(b: &mut Bencher) {arcswap(b, w(Set, GetHeavy))}
+#[bench]fn arcswap_get_mixed(b: &mut Bencher) {arcswap(b, w(Get, Mixed))}
+#[bench]fn arcswap_set_mixed(b: &mut Bencher) {arcswap(b, w(Set, Mixed))}
 #[bench]
-fn pairlock_set_single_alloc(b: &mut Bencher) {
-    let lock = PairLock::new(Arc::new(0), Arc::new(0));
-    b.iter(|| lock.set(Arc::new(0)) );
+fn arcswap_set_single_alloc(b: &mut Bencher) {
+    let lock = ArcSwap::new(Arc::new(0));
+    b.iter(|| lock.store(Arc::new(0)) );
+}
+
+
+/// left-right needs to know how to apply a set to both copies.
+#[derive(Clone)]
+struct LeftRight(Arc<AtomicIsize>);
+impl Absorb<Arc<AtomicIsize>> for LeftRight {
+    fn absorb_first(&mut self,  arc: &mut Arc<AtomicIsize>,  _: &Self) {
+        self.0 = arc.clone();
+    }
+    fn sync_with(&mut self,  first: &Self) {
+        self.0 = first.0.clone();
+    }
+}
+fn leftright(bencher: &mut Bencher,  workload: WorkLoad) {
+    let arc = Arc::new(AtomicIsize::new(0));
+    let (writer, reader) = left_right::new_from_empty(LeftRight(arc.clone()));
+    // there can only be one WriteHandle, and each thread needs its own reader
+    let writer = Arc::new(Mutex::new(writer));
+    let readers = reader.factory();
+    let get = |reader: &left_right::ReadHandle<LeftRight>| {
+        reader.enter().unwrap().0.clone()
+    };
+    for _ in 0..workload.other_getters {
+        let reader = readers.handle();
+        spawn(move|| {
+            get(&reader).fetch_add(1, SeqCst);
+            while get(&reader).load(Acquire) != -1
+                {}
+        });
+    }
+    for _ in 0..workload.other_setters {
+        let arc = arc.clone();
+        let writer = writer.clone();
+        spawn(move|| {
+            arc.fetch_add(1, SeqCst);
+            while arc.load(Acquire) != -1 {
+                writer.lock().unwrap().append(arc.clone()).publish();
+            }
+        });
+    }
+    // wait untill threads have started
+    while arc.load(Acquire) != workload.other_getters + workload.other_setters
+        {}
+    match workload.bench {
+        Some(Get) => bencher.iter(|| get(&reader) ),
+        Some(Set) => bencher.iter(|| {
+            writer.lock().unwrap().append(arc.clone()).publish();
+        }),
+        None => {}
+    }
+    arc.store(-1, Release);
 }
+#[bench]fn leftright_get_single(b: &mut Bencher) {leftright(b, w(Get, Single))}
+#[bench]fn leftright_set_single(b: &mut Bencher) {leftright(b, w(Set, Single))}
+#[bench]fn leftright_get_only(b: &mut Bencher) {leftright(b, w(Get, GetOnly))}
+#[bench]fn leftright_set_only(b: &mut Bencher) {leftright(b, w(Set, SetOnly))}
+#[bench]fn leftright_get_other_set(b: &mut Bencher) {leftright(b, w(Get, SetOnly))}
+#[bench]fn leftright_set_other_get(b: &mut Bencher) {leftright(b, w(Set, GetOnly))}
+#[bench]fn leftright_get_heavy(b: &mut Bencher) {leftright(b, w(Get, GetHeavy))}
+#[bench]fn leftright_set_heavy(b: &mut Bencher) {leftright(b, w(Set, SetHeavy))}
+#[bench]fn leftright_get_light(b: &mut Bencher) {leftright(b, w(Get, SetHeavy))}
+#[bench]fn leftright_set_light(b: &mut Bencher) {leftright(b, w(Set, GetHeavy))}
+#[bench]fn leftright_get_mixed(b: &mut Bencher) {leftright(b, w(Get, Mixed))}
+#[bench]fn leftright_set_mixed(b: &mut Bencher) {leftright(b, w(Set, Mixed))}
 
 
 fn rwlock(bencher: &mut Bencher,  workload: WorkLoad) {
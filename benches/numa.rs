@@ -0,0 +1,82 @@
+//! Measures how much reading a `PairLock` from a different NUMA node than the
+//! one its memory and its writer are on costs.
+//!
+//! core_affinity doesn't know about sockets, so the cores used are taken from
+//! the environment variables `NUMA_HOME_CORE` (default: the first core) and
+//! `NUMA_FAR_CORE` (default: the last core), which on most dual-socket Linux
+//! machines are on different sockets. `lscpu -e` shows the layout.
+//! `NUMA_NEAR_CORE` defaults to the core after the home core.
+//!
+//! On single-socket machines the near and far benchmarks should be the same,
+//! as should the `_idle` ones on any machine with a shared last-level cache.
+#![feature(test)]
+extern crate test;
+use test::{Bencher, black_box};
+extern crate core_affinity;
+use core_affinity::CoreId;
+extern crate pairlock;
+use pairlock::PairLock;
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::*;
+use std::thread::{spawn, scope};
+use std::env::var;
+use std::str::FromStr;
+
+fn core(env: &str,  default: fn(&[CoreId])->CoreId) -> CoreId {
+    let cores = core_affinity::get_core_ids().expect("get core ids");
+    match var(env) {
+        Ok(id) => CoreId{ id: usize::from_str(&id).unwrap() },
+        Err(_) => default(&cores),
+    }
+}
+fn home() -> CoreId {
+    core("NUMA_HOME_CORE", |cores| cores[0] )
+}
+fn near() -> CoreId {
+    core("NUMA_NEAR_CORE", |cores| cores[1 % cores.len()] )
+}
+fn far() -> CoreId {
+    core("NUMA_FAR_CORE", |cores| cores[cores.len()-1] )
+}
+
+/// Creates the lock on the home core so that the memory is allocated on its
+/// node, optionally keeps a writer running there, and views the lock from
+/// `reader`.
+fn view_from(bencher: &mut Bencher,  reader: CoreId,  writing: bool) {
+    let home = home();
+    let lock = spawn(move|| {
+        core_affinity::set_for_current(home);
+        Arc::new(PairLock::new(Box::new([0usize; 8]), Box::new([0usize; 8])))
+    }).join().unwrap();
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = if writing {
+        let (lock, stop) = (lock.clone(), stop.clone());
+        Some(spawn(move|| {
+            core_affinity::set_for_current(home);
+            let mut i = 0;
+            while !stop.load(Relaxed) {
+                i += 1;
+                lock.set(Box::new([i; 8]));
+            }
+        }))
+    } else {
+        None
+    };
+    // run the benchmark on another thread to not leave the harness pinned
+    scope(|s| s.spawn(|| {
+        core_affinity::set_for_current(reader);
+        bencher.iter(|| lock.view(|v| black_box(v[0]) ) );
+    }).join().unwrap() );
+    stop.store(true, Relaxed);
+    if let Some(writer) = writer {
+        writer.join().unwrap();
+    }
+}
+
+#[bench]fn view_home_idle(b: &mut Bencher) {view_from(b, home(), false)}
+#[bench]fn view_near_idle(b: &mut Bencher) {view_from(b, near(), false)}
+#[bench]fn view_far_idle(b: &mut Bencher) {view_from(b, far(), false)}
+#[bench]fn view_near_writing(b: &mut Bencher) {view_from(b, near(), true)}
+#[bench]fn view_far_writing(b: &mut Bencher) {view_from(b, far(), true)}
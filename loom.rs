@@ -0,0 +1,160 @@
+// Model checks with loom, run with
+// `RUSTFLAGS="--cfg loom" cargo test --release --test loom --features relaxed`
+#![cfg(loom)]
+
+extern crate pairlock;
+use pairlock::{PairLock,RingLock};
+
+use loom::cell::UnsafeCell;
+use loom::sync::Arc;
+use loom::thread;
+
+/// Two numbers that are always written together, accessed through loom's
+/// `UnsafeCell` so that loom reports reads that race with writes.
+struct Pair(UnsafeCell<(usize,usize)>);
+unsafe impl Sync for Pair {}
+impl Pair {
+    fn new(n: usize) -> Self {
+        Pair(UnsafeCell::new((n, n)))
+    }
+    fn get(&self) -> usize {
+        let (a, b) = self.0.with(|p| unsafe { *p } );
+        assert_eq!(a, b, "torn read");
+        a
+    }
+    fn set(&mut self,  n: usize) {
+        self.0.with_mut(|p| unsafe { *p = (n, n) } );
+    }
+}
+
+/// The value from the `get_big` benchmark in `benches/race_detection.rs`:
+/// every byte of the array changes with each new `i`, so any mix of two
+/// values is detected.
+struct Big(UnsafeCell<([u64; 4], u64)>);
+unsafe impl Sync for Big {}
+impl Big {
+    const PATTERN: u64 = 0x01_01_01_01_01_01_01_01;
+    fn new(i: u64) -> Self {
+        Big(UnsafeCell::new(([Self::PATTERN << (i & 7); 4], i)))
+    }
+    fn get(&self) -> u64 {
+        let (array, i) = self.0.with(|p| unsafe { *p } );
+        assert_eq!(array, [Self::PATTERN << (i & 7); 4], "race detected for {}", i);
+        i
+    }
+    fn set(&mut self,  i: u64) {
+        self.0.with_mut(|p| unsafe { *p = ([Self::PATTERN << (i & 7); 4], i) } );
+    }
+}
+
+fn update_and_views(new: fn(Pair, Pair) -> PairLock<Pair>) {
+    loom::model(move|| {
+        let pl = Arc::new(new(Pair::new(0), Pair::new(0)));
+        let readers = (0..2).map(|_| {
+            let pl = pl.clone();
+            thread::spawn(move|| {
+                let first = pl.view(Pair::get);
+                let second = pl.view(Pair::get);
+                assert!(first <= second);
+                assert!(second <= 2);
+            })
+        }).collect::<Vec<_>>();
+        for n in 1..=2 {
+            pl.update().set(n);
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(pl.view(Pair::get), 2);
+    });
+}
+
+#[test]
+fn update_and_views_seqcst() {
+    update_and_views(PairLock::new);
+}
+
+/// Checks that Acquire and Release are enough for the algorithm.
+#[cfg(feature="relaxed")]
+#[test]
+fn update_and_views_relaxed() {
+    update_and_views(PairLock::new_relaxed);
+}
+
+/// Two readers without a bound on preemptions takes minutes.
+#[test]
+fn get_big() {
+    let mut model = loom::model::Builder::new();
+    model.preemption_bound = Some(6);
+    model.check(|| {
+        let pl = Arc::new(PairLock::new(Big::new(3), Big::new(3)));
+        let readers = (0..2).map(|_| {
+            let pl = pl.clone();
+            thread::spawn(move|| {
+                let first = pl.view(Big::get);
+                let second = pl.view(Big::get);
+                assert!(first >= second);
+            })
+        }).collect::<Vec<_>>();
+        for i in (0..3).rev() {
+            pl.update().set(i);
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(pl.view(Big::get), 0);
+    });
+}
+
+#[test]
+fn set_and_get_arc() {
+    loom::model(|| {
+        let pl = Arc::new(PairLock::new(Arc::new(1), Arc::new(0)));
+        let reader = {
+            let pl = pl.clone();
+            thread::spawn(move|| {
+                let value = pl.get_clone();
+                assert!(*value == 1 || *value == 2);
+            })
+        };
+        pl.set(Arc::new(2));
+        reader.join().unwrap();
+        assert_eq!(*pl.get_clone(), 2);
+    });
+}
+
+#[test]
+fn try_update_with_view() {
+    loom::model(|| {
+        let pl = Arc::new(PairLock::new(Pair::new(1), Pair::new(0)));
+        let reader = {
+            let pl = pl.clone();
+            thread::spawn(move|| pl.view(Pair::get) )
+        };
+        if let Ok(mut guard) = pl.try_update() {
+            guard.set(2);
+        }
+        let seen = reader.join().unwrap();
+        assert!(seen == 1 || seen == 2);
+    });
+}
+
+#[test]
+fn ring_lock() {
+    loom::model(|| {
+        let rl = Arc::new(RingLock::new([Pair::new(0), Pair::new(0), Pair::new(0)]));
+        let reader = {
+            let rl = rl.clone();
+            thread::spawn(move|| {
+                let first = rl.view(Pair::get);
+                let second = rl.view(Pair::get);
+                assert!(first <= second);
+            })
+        };
+        for n in 1..=3 {
+            rl.update().set(n);
+        }
+        reader.join().unwrap();
+        assert_eq!(rl.view(Pair::get), 3);
+    });
+}
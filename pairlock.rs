@@ -6,25 +6,198 @@
  * copied, modified, or distributed except according to those terms.
  */
 
+#![cfg_attr(not(feature="std"), no_std)]
 #![allow(clippy::mutex_atomic)] // used for locking
 
-use std::cell::UnsafeCell;
-use std::sync::{Mutex,MutexGuard,TryLockError, Arc};
-use std::sync::atomic::{AtomicUsize,fence};
-use std::sync::atomic::Ordering::{SeqCst,Relaxed};
-use std::hint::spin_loop;
+extern crate alloc;
+
+use core::cell::UnsafeCell;
+use alloc::sync::{Arc,Weak};
+#[cfg(not(feature="std"))]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature="std")]
+use std::sync::mpsc::Sender;
+#[cfg(all(feature="std", not(feature="parking_lot"), not(loom)))]
+use std::sync::{Mutex,MutexGuard};
+#[cfg(all(feature="parking_lot", not(loom)))]
+use parking_lot::{Mutex,MutexGuard};
+#[cfg(loom)]
+use loom::sync::{Mutex,MutexGuard};
+#[cfg(any(loom, all(feature="std", not(feature="parking_lot"))))]
+use std::sync::TryLockError;
+#[cfg(not(loom))]
+use core::sync::atomic::{AtomicBool,AtomicUsize,AtomicU64,fence};
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicBool,AtomicUsize,AtomicU64,fence};
+use core::sync::atomic::Ordering::{self, SeqCst,AcqRel,Acquire,Release,Relaxed};
+#[cfg(not(loom))]
+use core::hint::spin_loop;
+#[cfg(loom)]
+use loom::hint::spin_loop;
+#[cfg(all(feature="std", not(loom)))]
 use std::thread::yield_now;
-use std::{ptr, mem};
-use std::ops::{Deref,DerefMut};
-use std::fmt::{self, Debug,Display};
+#[cfg(all(feature="park", not(loom)))]
+use std::thread::{self, Thread, park_timeout};
+#[cfg(loom)]
+use loom::thread::yield_now;
+#[cfg(all(feature="park", loom))]
+use loom::thread::{self, Thread};
+#[cfg(feature="std")]
+use std::time::{Duration,Instant};
+use core::{ptr, mem};
+use core::mem::ManuallyDrop;
+use core::marker::PhantomData;
+use core::ops::{Deref,DerefMut};
+use core::hash::{Hash,Hasher};
+use core::fmt::{self, Debug,Display};
+#[cfg(feature="std")]
 use std::error::Error;
+#[cfg(feature="std")]
+use std::io;
+#[cfg(feature="async")]
+use core::future::Future;
+#[cfg(feature="async")]
+use core::pin::Pin;
+#[cfg(feature="async")]
+use core::task::{Context, Poll, Waker};
+
+mod ringlock;
+pub use ringlock::{RingLock, RingUpdateGuard};
+
+const DEFAULT_UPDATE_SPINS: usize = 7; // not benchmarked
+/// Updates park instead of yielding after this many yields.
+const MAX_UPDATE_YIELDS: usize = 16; // not benchmarked either
+/// Bounds the cost of missed wakeups, as finishing reads only try to unpark
+/// the writer.
+#[cfg(all(feature="park", not(loom)))]
+const PARK_TIMEOUT: Duration = Duration::from_millis(1);
+/// Bits of `PairLock.waiting`
+#[cfg(feature="park")]
+const PARKED_WRITER: usize = 1;
+#[cfg(feature="async")]
+const WAITING_TASKS: usize = 2;
+/// Only checked by updates, so that reads don't wake `Watcher`s.
+#[cfg(feature="async")]
+const WATCHERS: usize = 4;
+/// `.guard_read()` aborts if the difference between started and finished
+/// read counts is larger than this, which leaves a wide margin before
+/// the difference wraps around at `isize::MAX`.
+#[cfg(feature="read-guard")]
+const MAX_UNFINISHED_READS: isize = isize::MAX / 2;
+/// The bits that finishing reads check.
+#[cfg(all(feature="park", feature="async"))]
+const WOKEN_BY_READS: usize = PARKED_WRITER | WAITING_TASKS;
+#[cfg(all(feature="park", not(feature="async")))]
+const WOKEN_BY_READS: usize = PARKED_WRITER;
+#[cfg(all(not(feature="park"), feature="async"))]
+const WOKEN_BY_READS: usize = WAITING_TASKS;
+
+// There is no poisoning, so a panic while holding the lock is simply ignored.
+// loom's Mutex has the same API as std's.
+#[cfg(any(loom, all(feature="std", not(feature="parking_lot"))))]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_,T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner() )
+}
+#[cfg(any(loom, all(feature="std", not(feature="parking_lot"))))]
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_,T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+#[cfg(any(loom, all(feature="std", not(feature="parking_lot"))))]
+fn get_mut<T>(mutex: &mut Mutex<T>) -> &mut T {
+    mutex.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner() )
+}
+#[cfg(all(feature="parking_lot", not(loom)))]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_,T> {
+    mutex.lock()
+}
+#[cfg(all(feature="parking_lot", not(loom)))]
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_,T>> {
+    mutex.try_lock()
+}
+#[cfg(all(feature="parking_lot", not(loom)))]
+fn get_mut<T>(mutex: &mut Mutex<T>) -> &mut T {
+    mutex.get_mut()
+}
 
-const MAX_UPDATE_SPINS: usize = 7; // not benchmarked
+/// A minimal spinlock used instead of `std::sync::Mutex` without `std`.
+#[cfg(not(feature="std"))]
+struct Mutex<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+#[cfg(not(feature="std"))]
+unsafe impl<T:Send> Sync for Mutex<T> {}
+#[cfg(not(feature="std"))]
+impl<T> Mutex<T> {
+    const fn new(value: T) -> Self {
+        Mutex { locked: AtomicBool::new(false),  value: UnsafeCell::new(value) }
+    }
+}
+#[cfg(not(feature="std"))]
+struct MutexGuard<'a, T:'a>(&'a Mutex<T>);
+#[cfg(not(feature="std"))]
+impl<'a, T> Deref for MutexGuard<'a,T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.0.value.get() }
+    }
+}
+#[cfg(not(feature="std"))]
+impl<'a, T> DerefMut for MutexGuard<'a,T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut*self.0.value.get() }
+    }
+}
+#[cfg(not(feature="std"))]
+impl<'a, T> Drop for MutexGuard<'a,T> {
+    fn drop(&mut self) {
+        self.0.locked.store(false, Release);
+    }
+}
+#[cfg(not(feature="std"))]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_,T> {
+    loop {
+        if let Some(guard) = try_lock(mutex) {
+            return guard;
+        }
+        while mutex.locked.load(Relaxed) {
+            spin_loop();
+        }
+    }
+}
+#[cfg(not(feature="std"))]
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_,T>> {
+    match mutex.locked.compare_exchange(false, true, Acquire, Relaxed) {
+        Ok(_) => Some(MutexGuard(mutex)),
+        Err(_) => None,
+    }
+}
+#[cfg(not(feature="std"))]
+fn get_mut<T>(mutex: &mut Mutex<T>) -> &mut T {
+    mutex.value.get_mut()
+}
+/// There is no scheduler to yield to without `std`.
+#[cfg(not(feature="std"))]
+fn yield_now() {
+    spin_loop();
+}
 
 /// A reader-writer lock with wait-free reads.
 ///
 /// Does not have poisoning.
 ///
+/// # Reading
+///
+/// * `.view()` works for any `T`, and lets a closure borrow the active value.
+/// * `.read()` copies the value out, and is the simplest for `Copy` types.
+/// * `.get()` clones the `Arc` of a `PairLock<Arc<T>>`, and `.view_inner()`
+///   borrows what it points to, which also works for `Arc<str>` and `Arc<[T]>`.
+/// * `.get_clone()` clones the value, and is meant for everything else.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -46,7 +219,7 @@ const MAX_UPDATE_SPINS: usize = 7; // not benchmarked
 ///     ac.set(Arc::new(load_config()));
 /// }
 /// ```
-pub struct PairLock<T> {
+pub struct PairLock<T, B=FixedSpins> {
     /// Incremented by two at the start of each view.
     /// Least significant bit stores index of the active slot.
     /// Wraparound is OK; only equality with completed_reads matter
@@ -60,23 +233,223 @@ pub struct PairLock<T> {
     /// lock used for serializing writes, stores the final read count of the
     /// inactive slot
     inactive_reads: Mutex<usize>,
+    /// Incremented by updates after the new value has become active.
+    /// Also used as the version of the active value by `.view_cached()`.
+    generation: AtomicU64,
+    /// The generation each value became active in, for `.view_generation()`.
+    /// Written together with the value, so reads of a slot see its current
+    /// generation.
+    slot_generations: [AtomicU64; 2],
+    /// Incremented when updates from `.update()` that had to wait make their
+    /// value active.
+    contended_writes: AtomicUsize,
+    /// How `.update()`s acquired the inactive value, for `.stats()`.
+    #[cfg(feature="metrics")]
+    update_counters: UpdateCounters,
+    /// Set by the constructors that opt out of default behavior.
+    options: Options,
+    /// Set by `new_synced()`, and given to every `UpdateGuard` as its
+    /// `sync_on_commit`.
+    #[cfg(feature="synced")]
+    sync_commits: Option<fn(&mut T, &T)>,
+    /// Cloned by updates and other methods that wait, to decide between
+    /// spinning and yielding.
+    backoff: B,
+    /// `PARKED_WRITER` is set while an update that waits for reads of the
+    /// inactive value is parked, `WAITING_TASKS` while `tasks` isn't empty
+    /// and `WATCHERS` while `watchers` isn't empty.
+    /// Without either feature there is nothing for finishing reads to wake.
+    #[cfg(any(feature="park", feature="async"))]
+    waiting: AtomicUsize,
+    /// The thread of the last update that parked.
+    #[cfg(feature="park")]
+    parked_writer: Mutex<Option<Thread>>,
+    /// Wakers of futures waiting for reads or another update to finish.
+    #[cfg(feature="async")]
+    tasks: Mutex<Vec<Waker>>,
+    /// Wakers of `Watcher`s waiting for the value to change.
+    #[cfg(feature="async")]
+    watchers: Mutex<Vec<Waker>>,
+    /// Called by updates after making a value active, while still holding
+    /// the lock.
+    on_commit: Mutex<Vec<CommitHook<T>>>,
+    /// Set when a hook is registered, so that updates of `PairLock`s without
+    /// hooks don't lock `on_commit`.
+    has_hooks: AtomicBool,
+}
+
+type CommitHook<T> = Box<dyn FnMut(&T)+Send>;
+/// Copies the new active value into the inactive slot after a commit.
+type SyncFn<T> = fn(&mut T, &T);
+
+/// Behavior chosen at construction, where each field requires a feature so
+/// that `PairLock`s of builds that don't use it don't grow.
+#[derive(Clone,Copy, Default)]
+struct Options {
+    /// Whether update guards that never gave out a mutable reference should
+    /// leave the active value active.
+    #[cfg(feature="no-rotate-on-clean")]
+    keep_on_clean: bool,
+    /// Whether views and updates use Acquire/Release instead of SeqCst.
+    #[cfg(feature="relaxed")]
+    relaxed: bool,
+}
+impl Options {
+    #[cfg(feature="no-rotate-on-clean")]
+    fn rotate_clean(self) -> bool {
+        !self.keep_on_clean
+    }
+    #[cfg(not(feature="no-rotate-on-clean"))]
+    fn rotate_clean(self) -> bool {
+        true
+    }
+    #[cfg(feature="relaxed")]
+    fn ordering(self,  weaker: Ordering) -> Ordering {
+        if self.relaxed {weaker} else {SeqCst}
+    }
+    #[cfg(not(feature="relaxed"))]
+    fn ordering(self,  _: Ordering) -> Ordering {
+        SeqCst
+    }
+}
+
+/// Finishes a read of the slot when dropped.
+struct Releaser<'a,T,B:Backoff>(&'a PairLock<T,B>, usize);
+impl<'a, T, B:Backoff> Drop for Releaser<'a,T,B> {
+    fn drop(&mut self) {
+        // Mark read as complete.
+        // Release prevents the reads from being reordered after this,
+        // and the update that sees this count then does an acquiring
+        // fence before touching the value.
+        // As all modifications of the counter are RMWs they continue
+        // the release sequence, so seeing the result of a later read's
+        // increment is enough to synchronize with this one.
+        // With the async feature a missed wakeup would never be
+        // recovered from, so SeqCst makes sure that either the waiting
+        // update sees this read finish or this read sees the flag.
+        #[cfg_attr(not(any(feature="park", feature="async")), allow(unused_variables))]
+        let (finish, check) = if cfg!(feature="async") {
+            (SeqCst, SeqCst)
+        } else {
+            (Release, Relaxed)
+        };
+        self.0.finished_reads[self.1].fetch_add(2, finish);
+        #[cfg(any(feature="park", feature="async"))]
+        if self.0.waiting.load(check) & WOKEN_BY_READS != 0 {
+            self.0.wake_waiting();
+        }
+    }
 }
 
-unsafe impl<T:Send> Send for PairLock<T> {}
+/// Initial read count of the second slot. Must be odd.
+///
+/// Starting at `!0` causes wraparound to be reached in tests, ensuring that
+/// it doesn't cause problems. Should be just as fast as starting at 1.
+#[cfg(feature="force-wraparound")]
+const SECOND_SLOT_START: usize = !0;
+#[cfg(not(feature="force-wraparound"))]
+const SECOND_SLOT_START: usize = 1;
+/// `.reset()` advances the generation to a multiple of this, so that the
+/// generation never goes backwards while `.total_writes()` can start over
+/// without storing where it did.
+const RESET_GENERATION_STEP: u64 = 1 << 32;
+
+unsafe impl<T:Send, B:Send> Send for PairLock<T,B> {}
 /// `T` must be `Send` because a shared reference can replace stored values.
-unsafe impl<T:Send+Sync> Sync for PairLock<T> {}
+/// `T` must be `Sync` because views give out shared references to the same
+/// value to multiple threads.
+///
+/// ```compile_fail
+/// fn assert_sync<T:Sync>() {}
+/// assert_sync::<pairlock::PairLock<std::cell::Cell<u32>>>();
+/// ```
+/// ```compile_fail
+/// fn assert_sync<T:Sync>() {}
+/// assert_sync::<pairlock::PairLock<std::sync::MutexGuard<'static, u32>>>();
+/// ```
+unsafe impl<T:Send+Sync, B:Sync> Sync for PairLock<T,B> {}
 
 impl<T> PairLock<T> {
     /// Creates a new `PairLock`.
     pub fn new(active: T,  inactive: T) -> Self {
+        Self::with_backoff(active, inactive, FixedSpins::default())
+    }
+    /// Creates a new `PairLock` where updates that never mutably dereference
+    /// their guard don't change the active value.
+    ///
+    /// With `new()`, an update makes the inactive value active even if
+    /// nothing was written to it, which can publish a stale value if the guard
+    /// is only inspected or dropped immediately.
+    /// Here such an update behaves like `UpdateGuard::cancel()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new_no_rotate_on_clean("foo", "bar");
+    /// let _ = lock.update();
+    /// assert_eq!(lock.read(), "foo");
+    /// *lock.update() = "baz";
+    /// assert_eq!(lock.read(), "baz");
+    /// ```
+    ///
+    /// Requires the `no-rotate-on-clean` feature.
+    #[cfg(feature="no-rotate-on-clean")]
+    pub fn new_no_rotate_on_clean(active: T,  inactive: T) -> Self {
+        let mut lock = Self::new(active, inactive);
+        lock.options.keep_on_clean = true;
+        lock
+    }
+    /// Creates a new `PairLock` whose views and updates use weaker memory
+    /// orderings.
+    ///
+    /// **Only use this after validating it on your target.**
+    /// The default orderings are conservative: Reads start with a `SeqCst`
+    /// increment, and updates use `SeqCst` fences and a `SeqCst` swap.
+    /// This uses `Acquire` for the increment, `AcqRel` for the swap, and
+    /// acquire and release fences, which is what the algorithm should need,
+    /// but has seen less testing.
+    ///
+    /// On x86 and x86_64 the generated code is identical, so there is nothing
+    /// to gain there. It may make reads faster on weakly ordered architectures
+    /// such as ARM and POWER.
+    ///
+    /// Other methods, such as `.quiesce()`, are not affected.
+    ///
+    /// Requires the `relaxed` feature.
+    #[cfg(feature="relaxed")]
+    pub fn new_relaxed(active: T,  inactive: T) -> Self {
+        let mut lock = Self::new(active, inactive);
+        lock.options.relaxed = true;
+        lock
+    }
+    /// Creates a new `PairLock` where both slots always contain the same
+    /// value, which hides the double buffering from updates.
+    ///
+    /// Every commit stores a clone of the new value in the other slot, as
+    /// `UpdateGuard::set_both()` does, so updates start out with the current
+    /// value, and an update that doesn't change anything leaves the value
+    /// unchanged. This costs a clone per update, and makes commits wait for
+    /// reads of the previous value while holding the lock.
+    ///
+    /// Changes made before `UpdateGuard::cancel()` are not undone, and will be
+    /// seen by the next update.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new_synced(vec![1]);
+    /// let _ = lock.update();
+    /// assert_eq!(lock.get_clone(), [1]);
+    /// lock.update().push(2);
+    /// assert_eq!(*lock.update(), [1, 2]);
+    /// ```
+    ///
+    /// Requires the `synced` feature.
+    #[cfg(feature="synced")]
+    pub fn new_synced(value: T) -> Self where T: Clone {
         PairLock {
-            reads_active: AtomicUsize::new(0),
-            // Initializing the second slot to !0 causes wraparound to be
-            // reached in tests, ensuring that it doesn't cause problems.
-            // Should be just as fast as initializing to 1.
-            finished_reads: [AtomicUsize::new(0), AtomicUsize::new(!0)],
-            values: [UnsafeCell::new(active), UnsafeCell::new(inactive)],
-            inactive_reads: Mutex::new(!0),
+            sync_commits: Some(|inactive, active| inactive.clone_from(active) ),
+            ..Self::with_clone(value)
         }
     }
     /// Creates a new `PairLock` with `init` as the active value
@@ -90,35 +463,222 @@ impl<T> PairLock<T> {
         let second = init.clone();
         Self::new(init, second)
     }
+}
+impl<T, B:Backoff> PairLock<T,B> {
+    /// Creates a new `PairLock` whose updates use `backoff` when waiting.
+    /// See `PairLockBuilder` for a more convenient way to do this.
+    pub fn with_backoff(active: T,  inactive: T,  backoff: B) -> Self {
+        PairLock {
+            reads_active: AtomicUsize::new(0),
+            finished_reads: [AtomicUsize::new(0), AtomicUsize::new(SECOND_SLOT_START)],
+            values: [UnsafeCell::new(active), UnsafeCell::new(inactive)],
+            inactive_reads: Mutex::new(SECOND_SLOT_START),
+            generation: AtomicU64::new(0),
+            slot_generations: [AtomicU64::new(0), AtomicU64::new(0)],
+            contended_writes: AtomicUsize::new(0),
+            #[cfg(feature="metrics")]
+            update_counters: UpdateCounters::default(),
+            options: Options::default(),
+            #[cfg(feature="synced")]
+            sync_commits: None,
+            backoff,
+            #[cfg(any(feature="park", feature="async"))]
+            waiting: AtomicUsize::new(0),
+            #[cfg(feature="park")]
+            parked_writer: Mutex::new(None),
+            #[cfg(feature="async")]
+            tasks: Mutex::new(Vec::new()),
+            #[cfg(feature="async")]
+            watchers: Mutex::new(Vec::new()),
+            on_commit: Mutex::new(Vec::new()),
+            has_hooks: AtomicBool::new(false),
+        }
+    }
+    /// Returns `weaker` if created with `new_relaxed()`, and `SeqCst`
+    /// otherwise.
+    fn ordering(&self,  weaker: Ordering) -> Ordering {
+        self.options.ordering(weaker)
+    }
 
 
     /// View the active value of this `PairLock` inside a closure.
     ///
     /// Views should be short-lived to avoid blocking subsequent updates.
     ///
-    /// Reads must be performed inside a closure, because preventing memory
+    /// Reads are performed inside a closure, because preventing memory
     /// unsafety in the face of repeated `mem::forget()`s of a read guard is
-    /// non-trivial.
+    /// non-trivial. `.guard_read()` does it at the cost of a check.
     ///
     /// Will never block in any way, and should run in constant time.
+    /// This means that views of different `PairLock`s can be nested in any
+    /// order without risk of deadlock, unlike `RwLock::read()`, and that no
+    /// lock ordering needs to be followed for them. (Nested updates of
+    /// different locks are however subject to the usual rules.)
     pub fn view<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> R {
         unsafe {
-            // Acquire/Release doesn't work across different variables
-            let active = self.reads_active.fetch_add(2, SeqCst);
-            let slot = active & 1;
-            // not releasing on unwind could cause use-after-free
-            struct Releaser<'a>(&'a AtomicUsize);
-            impl<'a> Drop for Releaser<'a> {
-                fn drop(&mut self) {
-                    // reads and release must not mix
-                    fence(SeqCst);
-                    // mark read as complete
-                    self.0.fetch_add(2, Relaxed);
-                }
+            // Acquire synchronizes with the swap in make_active(), as all
+            // changes of reads_active are RMWs, and the loom model
+            // update_and_views_relaxed confirms that Acquire and Release are
+            // enough. SeqCst is still the default because it has seen more
+            // testing on hardware: on x86 both are a `lock xadd`, so it only
+            // costs anything on weakly ordered architectures, where
+            // new_relaxed() opts out of it. Finishing the read only needs
+            // Release, unless the async feature requires SeqCst (see Releaser).
+            let active = self.reads_active.fetch_add(2, self.ordering(Acquire));
+            self.view_started(active & 1, viewer)
+        }
+    }
+    /// Runs `viewer` on the value in `slot` and then marks the read as finished.
+    ///
+    /// # Safety
+    /// The caller must have registered the read by incrementing
+    /// `reads_active` while `slot` was active.
+    unsafe fn view_started<F:FnOnce(&T)->R,R>(&self,  slot: usize,  viewer: F) -> R {
+        // not releasing on unwind could cause use-after-free
+        let _defer = Releaser(self, slot);
+        viewer(&*self.values[slot].get())
+    }
+    /// Starts a read that lasts until the returned guard is dropped, instead
+    /// of being limited to a closure like `.view()`.
+    ///
+    /// Requires the `read-guard` feature.
+    ///
+    /// This makes it possible to return the reference from a function or to
+    /// hold it across an `.await`. The downsides are that updates will block
+    /// for as long as guards of the inactive value are alive, and that
+    /// `mem::forget()`ing a guard blocks all updates after the next one
+    /// forever.
+    /// Forgetting guards could also make the read count wrap around and let
+    /// an update modify a value that is being read, so this aborts the
+    /// process if there are implausibly many unfinished reads, like `Arc`
+    /// does on reference count overflow. That check is the only extra cost
+    /// compared to `.view()`.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1], Vec::new());
+    /// let guard = lock.guard_read();
+    /// lock.set(vec![2]);
+    /// assert_eq!(*guard, [1]);
+    /// assert!(lock.try_update().is_err());
+    /// drop(guard);
+    /// assert!(lock.try_update().is_ok());
+    /// ```
+    #[cfg(feature="read-guard")]
+    pub fn guard_read(&self) -> ReadGuard<'_,T,B> {
+        let started = self.reads_active.fetch_add(2, self.ordering(Acquire));
+        let slot = started & 1;
+        // Can underestimate because reads that started after this one might
+        // have finished, but not by anything close to the margin.
+        let finished = self.finished_reads[slot].load(Relaxed);
+        if started.wrapping_sub(finished) as isize > MAX_UNFINISHED_READS {
+            std::process::abort();
+        }
+        ReadGuard {
+            value: unsafe { &*self.values[slot].get() },
+            _releaser: Releaser(self, slot),
+        }
+    }
+    /// View the active value inside a closure, and panic in debug builds if
+    /// that takes longer than `max`.
+    ///
+    /// This is for catching reads that are held too long, such as across IO,
+    /// in tests. The panic happens after `viewer` has returned and the read
+    /// has finished. In release builds this is identical to `.view()`.
+    ///
+    /// Holding a read across an `.await` is not possible with either method,
+    /// as the closure cannot return something that borrows the value:
+    ///
+    /// ```compile_fail
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1], vec![0]);
+    /// let future = lock.view(|v| async move { v.len() } );
+    /// ```
+    ///
+    /// # Panics
+    /// If `viewer` ran for longer than `max` and debug assertions are enabled.
+    #[cfg(feature="std")]
+    pub fn view_bounded<F:FnOnce(&T)->R,R>(&self,  max: Duration,  viewer: F) -> R {
+        #[cfg(debug_assertions)]
+        let started = Instant::now();
+        let result = self.view(viewer);
+        #[cfg(debug_assertions)] {
+            let elapsed = started.elapsed();
+            if elapsed > max {
+                panic!("view took {:?}, which is longer than the limit of {:?}", elapsed, max);
             }
-            let _defer = Releaser(&self.finished_reads[slot]);
-            viewer(&*self.values[slot].get())
         }
+        #[cfg(not(debug_assertions))]
+        let _ = max;
+        result
+    }
+    /// View the active value through a trait object.
+    ///
+    /// Unlike `.view()`, this isn't instantiated for every closure it's called
+    /// with, which reduces code size when there are many different viewers,
+    /// such as a registry of observers. The cost is a dynamic call.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn view_dyn(&self,  viewer: &mut dyn FnMut(&T)) {
+        self.view(|v| viewer(v) )
+    }
+    /// View the active value through a `ReadRef`, which reads more naturally
+    /// than a bare `&T` when `body` consists of several statements.
+    ///
+    /// The `ReadRef` cannot escape `body`, so the read is finished when it
+    /// returns, exactly as with `.view()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![3, 1, 2], Vec::new());
+    /// let (min, max) = lock.read_scope(|numbers| {
+    ///     assert!(!numbers.is_empty());
+    ///     let min = numbers.iter().min().copied();
+    ///     let max = numbers.iter().max().copied();
+    ///     (min, max)
+    /// });
+    /// assert_eq!((min, max), (Some(1), Some(3)));
+    /// ```
+    ///
+    /// ```compile_fail
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// let escaped = lock.read_scope(|r| r );
+    /// ```
+    pub fn read_scope<F:FnOnce(ReadRef<'_,T>)->R,R>(&self,  body: F) -> R {
+        self.view(|v| body(ReadRef(v)) )
+    }
+    /// View the active value inside a closure, but only if no update is in
+    /// progress.
+    ///
+    /// Returns `None` without calling `viewer` if an update holds the lock.
+    /// Otherwise the read starts while the lock is held, so the value seen was
+    /// not about to be replaced by an update that had already started.
+    /// Updates can however start and finish while `viewer` runs, exactly as
+    /// with `.view()`.
+    ///
+    /// Never blocks, but isn't wait-free either, as it will fail for as long as
+    /// updates keep happening.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// assert_eq!(lock.view_if_stable(|v| *v ), Some(1));
+    /// let _guard = lock.update();
+    /// assert_eq!(lock.view_if_stable(|v| *v ), None);
+    /// ```
+    pub fn view_if_stable<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> Option<R> {
+        let guard = try_lock(&self.inactive_reads)?;
+        Some(self.view(|v| {
+            // let updates through once the read has been registered
+            drop(guard);
+            viewer(v)
+        }))
     }
     /// Returns a clone of the active value.
     ///
@@ -126,6 +686,166 @@ impl<T> PairLock<T> {
     pub fn get_clone(&self) -> T where T: Clone {
         self.view(|v| v.clone() )
     }
+    /// Copies a field out of the active value.
+    ///
+    /// This is `.view()` restricted to `Copy` results, for call sites where
+    /// extracting a small part is the intent.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// struct Config { timeout: u32,  name: String }
+    /// let lock = PairLock::with_clone(std::sync::Arc::new(Config {
+    ///     timeout: 30,
+    ///     name: "foo".to_string(),
+    /// }));
+    /// assert_eq!(lock.get_field(|c| c.timeout ), 30);
+    /// assert_eq!(lock.get_cloned_field(|c| &c.name ), "foo");
+    /// ```
+    pub fn get_field<F:FnOnce(&T)->R,R:Copy>(&self,  field: F) -> R {
+        self.view(field)
+    }
+    /// Clones a part of the active value that `field` borrows.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn get_cloned_field<F:FnOnce(&T)->&R,R:Clone>(&self,  field: F) -> R {
+        self.view(|v| field(v).clone() )
+    }
+    /// Returns the result of `f` on the active value, but reuses the result
+    /// stored in `cache` if the value hasn't been replaced since it was
+    /// computed.
+    ///
+    /// `cache` is owned by the caller, such as a field in a per-thread struct,
+    /// and stores the result together with the generation of the value it was
+    /// computed from (see `.generation()`).
+    /// `f` is only called when the generation has changed.
+    ///
+    /// The generation is loaded before the read of the value starts, so a result
+    /// is never computed from a value older than the version it's stored
+    /// under. A concurrent update can make it newer, which only means the
+    /// next call will compute it again.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1, 2, 3], Vec::new());
+    /// let mut cache = None;
+    /// let sum = |v: &Vec<i32>| v.iter().sum::<i32>();
+    /// assert_eq!(lock.view_cached(&mut cache, sum), 6);
+    /// assert_eq!(lock.view_cached(&mut cache, |_| unreachable!() ), 6);
+    /// lock.set(vec![4]);
+    /// assert_eq!(lock.view_cached(&mut cache, sum), 4);
+    /// ```
+    pub fn view_cached<F:FnOnce(&T)->R,R:Clone>
+    (&self,  cache: &mut Option<(u64,R)>,  f: F) -> R {
+        let version = self.generation();
+        match cache {
+            Some((cached, result)) if *cached == version => result.clone(),
+            _ => {
+                let result = self.view(f);
+                *cache = Some((version, result.clone()));
+                result
+            }
+        }
+    }
+
+    /// Like `.view()`, but also returns the generation (see `.generation()`)
+    /// of the value `viewer` saw.
+    ///
+    /// Unlike comparing `.generation()` before and after, the generation is
+    /// exact: two views that return the same generation saw the same value,
+    /// and different generations mean an update happened in between.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// let (a, first) = lock.view_generation(|v| *v );
+    /// let (b, second) = lock.view_generation(|v| *v );
+    /// assert_eq!((a, first), (b, second));
+    /// lock.set(2);
+    /// assert_eq!(lock.view_generation(|v| *v ), (2, first+1));
+    /// ```
+    pub fn view_generation<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> (R,u64) {
+        unsafe {
+            let active = self.reads_active.fetch_add(2, self.ordering(Acquire));
+            let slot = active & 1;
+            // written before the value was made active, which the increment
+            // synchronized with
+            let generation = self.slot_generations[slot].load(Relaxed);
+            (self.view_started(slot, viewer), generation)
+        }
+    }
+
+    /// View the active value without registering as a reader.
+    ///
+    /// This skips the atomic increments that `view()` performs, which means
+    /// nothing stops an update from mutating the value while `viewer` is
+    /// looking at it.
+    ///
+    /// # Safety
+    /// **This is a data race unless no update can happen while `viewer` runs.**
+    /// If the slot read from becomes inactive and is then modified by
+    /// `.update()`, `.set()` or similar, the behavior is undefined, even for
+    /// types where a torn value would be harmless, and even if the value is
+    /// validated afterwards.
+    ///
+    /// It is only intended for experiments where updates are known to be
+    /// paused, for example by external synchronization; prefer `.view()`.
+    pub unsafe fn peek_unsync<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> R {
+        let slot = self.reads_active.load(SeqCst) & 1;
+        viewer(&*self.values[slot].get())
+    }
+    /// Returns a copy of the active value by peeking at it until two
+    /// consecutive peeks agree.
+    ///
+    /// This catches torn values from updates that complete while peeking,
+    /// but cannot remove the data race itself.
+    ///
+    /// # Safety
+    /// Same as for `.peek_unsync()`: no update must modify the slot that is
+    /// being read from while this method runs.
+    pub unsafe fn peek_consistent(&self) -> T where T: Copy+PartialEq {
+        let mut prev = self.peek_unsync(|v| *v );
+        loop {
+            let current = self.peek_unsync(|v| *v );
+            if current == prev {
+                return current;
+            }
+            prev = current;
+        }
+    }
+    /// Returns a reference to the inactive value without locking.
+    ///
+    /// The inactive slot is the one *not* selected by the least significant
+    /// bit of the started reads counter, and is the one the next update will
+    /// modify. This is useful for asserting what `UpdateGuard::cancel()` left
+    /// behind, or for setup code that only has a shared reference.
+    ///
+    /// # Safety
+    /// No update must be in progress or start while the returned reference
+    /// is alive, including through an `UpdateGuard` held by the caller.
+    /// Updates modify the inactive slot, and also make it active, after which
+    /// the reference would point to the value readers see.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::new(1, 0);
+    /// let mut guard = lock.update();
+    /// *guard = 2;
+    /// UpdateGuard::cancel(guard);
+    /// assert_eq!(unsafe { *lock.peek_inactive() }, 2);
+    /// assert_eq!(lock.read(), 1);
+    /// ```
+    pub unsafe fn peek_inactive(&self) -> &T {
+        let slot = self.reads_active.load(SeqCst) & 1;
+        &*self.values[slot ^ 1].get()
+    }
 
 
     /// Creates an UpdateGuard if there are no unfinished reads of the inactive
@@ -134,12 +854,23 @@ impl<T> PairLock<T> {
     /// # Safety
     /// The mutex guard must be for the mutex in self.
     unsafe fn check_inactive<'a>(&'a self,  inactive_reads: MutexGuard<'a,usize>)
-    -> Result<UpdateGuard<'a,T>, MutexGuard<'a,usize>> {
+    -> Result<UpdateGuard<'a,T,B>, MutexGuard<'a,usize>> {
         let slot = *inactive_reads & 1;
         // make sure that all views of the previous value has finished
         if self.finished_reads[slot].load(Relaxed) == *inactive_reads {
-            fence(SeqCst);
-            Ok(UpdateGuard{ guard: inactive_reads,  pl: self })
+            fence(self.ordering(Acquire));
+            Ok(UpdateGuard{
+                lock: UpdateLock {
+                    guard: ManuallyDrop::new(inactive_reads),
+                    pl: self,
+                    contended: false,
+                },
+                dirty: false,
+                #[cfg(feature="synced")]
+                sync_on_commit: self.sync_commits,
+                #[cfg(not(feature="synced"))]
+                sync_on_commit: None,
+            })
         } else {
             Err(inactive_reads)
         }
@@ -149,7 +880,14 @@ impl<T> PairLock<T> {
     /// a RAII guard that will make it active when the guard is dropped.
     /// 
     /// Will block the thread waiting for reads of the inactive value or other
-    /// updates to finish.
+    /// updates to finish. When waiting for reads it first spins and yields,
+    /// and then, with the default `park` feature, parks the thread until a
+    /// read finishes, so that a slow read doesn't make it burn CPU.
+    /// Reads that start while waiting see the active value and are not waited
+    /// for, so updates cannot be starved by a flood of reads: the wait is
+    /// bounded by the longest read that was in progress when the inactive
+    /// value was last replaced. Readers therefore never need to back off for
+    /// a waiting writer.
     ///
     /// Panicing while holding the guard does not poison the lock.
     ///
@@ -178,7 +916,8 @@ impl<T> PairLock<T> {
     /// lock.view(|v| assert_eq!(v[..], ["foo","bar","baz"][..]) );
     /// ```
     ///
-    /// Doing nothing with the guard, and still changing the value of the lock:
+    /// Doing nothing with the guard, and still changing the value of the lock
+    /// (unless it was created with `new_no_rotate_on_clean()` or `new_synced()`):
     /// ```
     /// # use pairlock::PairLock;
     /// let lock = PairLock::new("foo", "bar");
@@ -186,24 +925,157 @@ impl<T> PairLock<T> {
     /// let _ = lock.update();
     /// assert_eq!(lock.read(), "bar");
     /// ```
-    pub fn update(&self) -> UpdateGuard<T> {
+    pub fn update(&self) -> UpdateGuard<'_,T,B> {
+        self.update_counting().0
+    }
+    /// `.update()` which also returns how many times it had to wait.
+    /// Waiting for the lock counts once, and each failed check for unfinished
+    /// reads of the inactive value counts once.
+    fn update_counting(&self) -> (UpdateGuard<'_,T,B>, usize) {
+        let mut waits = 0;
+        let mut yields = 0;
+        let mut backoff = self.backoff.clone();
         loop {
             unsafe {
-                let mut inactive_reads = self.inactive_reads.lock()
-                    .unwrap_or_else(|poisoned| poisoned.into_inner() );
-                for _ in 0..MAX_UPDATE_SPINS {
-                    inactive_reads = match self.check_inactive(inactive_reads) {
-                        Ok(success) => return success,
-                        Err(retry) => retry
-                    };
-                    spin_loop();
+                let mut inactive_reads = match try_lock(&self.inactive_reads) {
+                    Some(guard) => guard,
+                    None => {
+                        waits += 1;
+                        lock(&self.inactive_reads)
+                    }
+                };
+                loop {
+                    backoff.reset();
+                    loop {
+                        inactive_reads = match self.check_inactive(inactive_reads) {
+                            Ok(mut success) => {
+                                if waits != 0 {
+                                    success.lock.contended = true;
+                                } else {
+                                    self.record(UpdateEvent::FastAcquisition);
+                                }
+                                return (success, waits);
+                            }
+                            Err(retry) => retry
+                        };
+                        waits += 1;
+                        if !backoff.spin() {
+                            break;
+                        }
+                        self.record(UpdateEvent::Spin);
+                    }
+                    if yields < MAX_UPDATE_YIELDS {
+                        break;
+                    }
+                    // a read is taking long, so sleep instead of burning CPU
+                    self.park_for_reads(&inactive_reads);
                 }
                 // release lock before yielding
                 drop(inactive_reads);
             }
+            yields += 1;
+            self.record(UpdateEvent::Yield);
             yield_now();
         }
     }
+    #[cfg(feature="metrics")]
+    fn record(&self,  event: UpdateEvent) {
+        let counter = match event {
+            UpdateEvent::FastAcquisition => &self.update_counters.fast_acquisitions,
+            UpdateEvent::Spin => &self.update_counters.spins,
+            UpdateEvent::Yield => &self.update_counters.yields,
+        };
+        counter.fetch_add(1, Relaxed);
+    }
+    #[cfg(not(feature="metrics"))]
+    #[inline(always)]
+    fn record(&self,  _: UpdateEvent) {}
+
+    /// Runs `f` with the inactive value and the active value while holding the
+    /// update lock, and then makes the inactive value active.
+    ///
+    /// Because updates are serialized, `f` can also modify other state that
+    /// must be kept consistent with this `PairLock`, without other updates
+    /// interfering.
+    /// `f` should be short to avoid blocking other updates, and must not
+    /// update this `PairLock`, as that would deadlock.
+    ///
+    /// If `f` panics the lock is released, and the inactive value becomes
+    /// active, as when an `UpdateGuard` is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1], Vec::new());
+    /// let len = lock.update_with_lock(|new, old| {
+    ///     new.clone_from(old);
+    ///     new.push(2);
+    ///     new.len()
+    /// });
+    /// assert_eq!(len, 2);
+    /// assert_eq!(lock.get_clone(), [1, 2]);
+    /// ```
+    #[doc(alias = "update_with")]
+    pub fn update_with_lock<F:FnOnce(&mut T,&T)->R,R>(&self,  f: F) -> R {
+        let mut guard = self.update();
+        let (inactive, active) = UpdateGuard::both(&mut guard);
+        f(inactive, active)
+    }
+    /// Runs `f` with an `UpdateGuard`, and then makes the inactive value
+    /// active.
+    ///
+    /// Like `.update_with_lock()`, but `f` gets the whole guard, so it can
+    /// use methods such as `UpdateGuard::active()` across several steps of
+    /// one update.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::with_clone(vec![1]);
+    /// lock.with_update(|guard| {
+    ///     let (new, old) = UpdateGuard::both(guard);
+    ///     new.clone_from(old);
+    ///     guard.push(2);
+    ///     guard.push(3);
+    /// });
+    /// assert_eq!(lock.get_clone(), [1, 2, 3]);
+    /// ```
+    pub fn with_update<F:FnOnce(&mut UpdateGuard<'_,T,B>)->R,R>(&self,  f: F) -> R {
+        f(&mut self.update())
+    }
+    /// Runs `f` with the inactive value and the active value while holding the
+    /// update lock, like `.update_with_lock()`, but doesn't make the inactive
+    /// value active afterwards.
+    ///
+    /// This is for preparing the next value ahead of time, such as cloning
+    /// the active value into the inactive buffer: the changes are kept, and
+    /// the next `.update()` will find them in its guard. Other updates can
+    /// happen in between, and would then start with the prepared value.
+    /// If `f` panics, the value isn't made active either.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1], Vec::new());
+    /// lock.prepare(|next, current| next.clone_from(current) );
+    /// assert_eq!(lock.get_clone(), [1]);
+    /// lock.update().push(2);
+    /// assert_eq!(lock.get_clone(), [1, 2]);
+    /// ```
+    pub fn prepare<F:FnOnce(&mut T,&T)->R,R>(&self,  f: F) -> R {
+        // also cancels if f panics
+        struct Cancel<'a,T,B:Backoff>(Option<UpdateGuard<'a,T,B>>);
+        impl<'a, T, B:Backoff> Drop for Cancel<'a,T,B> {
+            fn drop(&mut self) {
+                if let Some(guard) = self.0.take() {
+                    UpdateGuard::cancel(guard);
+                }
+            }
+        }
+        let mut cancel = Cancel(Some(self.update()));
+        let (inactive, active) = UpdateGuard::both(cancel.0.as_mut().unwrap());
+        f(inactive, active)
+    }
 
     /// Attempts to lock the inactive value, giving exclusive access to it
     /// through a RAII guard that will make it active when the guard is dropped.
@@ -219,99 +1091,1303 @@ impl<T> PairLock<T> {
     /// let _guard = pl.try_update().unwrap();
     /// assert_eq!(pl.try_update(), Err(TryUpdateError::OtherUpdate));
     /// ```
-    pub fn try_update(&self) -> Result<UpdateGuard<T>,TryUpdateError> {
+    pub fn try_update(&self) -> Result<UpdateGuard<'_,T,B>,TryUpdateError> {
         unsafe {
-            let guard = match self.inactive_reads.try_lock() {
-                Ok(guard) => guard,
-                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
-                Err(TryLockError::WouldBlock) => return Err(TryUpdateError::OtherUpdate)
+            let guard = match try_lock(&self.inactive_reads) {
+                Some(guard) => guard,
+                None => return Err(TryUpdateError::OtherUpdate)
             };
             self.check_inactive(guard).map_err(|_| TryUpdateError::InactiveReads )
         }
     }
 
-    /// Stores a new value in the `PairLock`,
-    /// returning the previously inactive value.
+    /// Like `.update()`, but gives up if it hasn't succeeded within `timeout`.
     ///
-    /// Will block if another update/replace/set is in progress.
-    /// if there are reads of the second last value that haven't finished yet.  
-    pub fn set(&self,  value: T) -> T {
-        mem::replace(&mut*self.update(), value)
-    }
-
-
-    /// Consumes the `PairLock` and returns the active and inactive values.
+    /// Waiting for another update is also done by polling, so the timeout is
+    /// honored for both phases.
+    /// A timeout too long for the deadline to be representable as an
+    /// `Instant` means no deadline: it then keeps polling until it succeeds.
+    ///
+    /// # Errors
+    /// Returns the error from the last `.try_update()` attempt, which tells
+    /// which phase was still blocking when the time ran out.
     ///
     /// # Examples
     /// ```
-    /// # use pairlock::PairLock;
-    /// let lock = PairLock::new(true, false);
-    /// let (active, inactive) = lock.into_inner();
+    /// # use pairlock::{PairLock,TryUpdateError};
+    /// # use std::time::Duration;
+    /// let pl = PairLock::new(1, 0);
+    /// let guard = pl.update_timeout(Duration::from_millis(10)).unwrap();
+    /// let result = pl.update_timeout(Duration::from_millis(10));
+    /// assert_eq!(result.unwrap_err(), TryUpdateError::OtherUpdate);
     /// ```
-    pub fn into_inner(self) -> (T, T) {
-        // yay no custom drop impl
-        let PairLock{ reads_active, values, .. } = self;
-        let active = reads_active.into_inner() & 1;
-        // but cannot destructure fixed-size arrays :(
-        unsafe {
-            let active_ = ptr::read(values[active].get());
-            let inactive_ = ptr::read(values[active^1].get());
-            mem::forget(values);
-            (active_, inactive_)
+    #[cfg(feature="std")]
+    pub fn update_timeout(&self,  timeout: Duration)
+    -> Result<UpdateGuard<'_,T,B>,TryUpdateError> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.update_deadline(deadline),
+            None => self.update_until(|| false ),
         }
     }
-
-    /// Given exclusive access this method returns mutable references to both
-    /// the active and inactive value.
+    /// Like `.update()`, but gives up if it hasn't succeeded by `deadline`.
     ///
-    /// # Examples
-    /// ```
+    /// This is `.update_timeout()` with an absolute time, for when several
+    /// attempts share a deadline. If the deadline has already passed, a single
+    /// attempt is made, as with `.try_update()`.
+    ///
+    /// # Errors
+    /// Returns the error from the last `.try_update()` attempt, which tells
+    /// which phase was still blocking when the time ran out.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,TryUpdateError};
+    /// # use std::time::Instant;
+    /// let pl = PairLock::new(1, 0);
+    /// let _guard = pl.update_deadline(Instant::now()).unwrap();
+    /// let result = pl.update_deadline(Instant::now());
+    /// assert_eq!(result.unwrap_err(), TryUpdateError::OtherUpdate);
+    /// ```
+    #[cfg(feature="std")]
+    pub fn update_deadline(&self,  deadline: Instant)
+    -> Result<UpdateGuard<'_,T,B>,TryUpdateError> {
+        self.update_until(|| Instant::now() >= deadline )
+    }
+    /// Polls `.try_update()` until it succeeds or `timed_out()` returns true.
+    #[cfg(feature="std")]
+    fn update_until<F:Fn()->bool>(&self,  timed_out: F)
+    -> Result<UpdateGuard<'_,T,B>,TryUpdateError> {
+        let mut backoff = self.backoff.clone();
+        backoff.reset();
+        // only check the time on entry and after yielding
+        let mut check_time = true;
+        loop {
+            match self.try_update() {
+                Ok(guard) => return Ok(guard),
+                Err(error) if check_time && timed_out() => return Err(error),
+                Err(_) => {}
+            }
+            check_time = !backoff.spin();
+            if check_time {
+                yield_now();
+                backoff.reset();
+            }
+        }
+    }
+
+    /// Returns a future that resolves to an `UpdateGuard` without blocking the
+    /// thread, for use in async code.
+    ///
+    /// Instead of spinning or parking, the future is woken when a read
+    /// finishes or another update releases the lock, and then tries again.
+    /// The guard is the same as the one from `.update()`, and shouldn't be
+    /// held across `.await`s, as other updates would block on it.
+    ///
+    /// `.view()` stays synchronous and wait-free. With this feature enabled
+    /// the read counter is incremented with `SeqCst` instead of `Release`
+    /// when finishing a read, which only makes a difference on weakly ordered
+    /// architectures.
+    ///
+    /// Requires the `async` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// async fn reload(config: &PairLock<String>) {
+    ///     let mut guard = config.update_async().await;
+    ///     guard.clear();
+    ///     guard.push_str("reloaded");
+    /// }
+    /// ```
+    #[cfg(feature="async")]
+    pub fn update_async(&self) -> UpdateFuture<'_,T,B> {
+        UpdateFuture { pl: self }
+    }
+    /// Creates a `Watcher` whose `.recv()` returns a clone of the active value
+    /// every time it has changed, starting with the current one.
+    ///
+    /// Changes are detected through `.generation()`, so updates that happen
+    /// between calls of `.recv()` are coalesced, and only the newest value is
+    /// returned. Reads don't wake watchers, only updates do.
+    ///
+    /// Requires the `async` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// async fn log_changes(config: &PairLock<String>) {
+    ///     let mut watcher = config.watch();
+    ///     loop {
+    ///         println!("config is now {}", watcher.recv().await);
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature="async")]
+    pub fn watch(&self) -> Watcher<'_,T,B> where T: Clone {
+        Watcher { pl: self,  seen: None }
+    }
+
+    /// Gives mutable access to the inactive value without making it active,
+    /// if that can be done without blocking.
+    ///
+    /// This is for double buffering where the value that was replaced by the
+    /// last update is reused for the next one: After `.set()` or an update
+    /// the previous value might still be read, but once those reads are done
+    /// this can be used to start preparing the next value ahead of time.
+    /// The changes are kept for the next update, as with
+    /// `UpdateGuard::cancel()`.
+    ///
+    /// # Errors
+    /// Fails the same way as `.try_update()`, and then doesn't call `f`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1; 4], vec![0; 4]);
+    /// let mut frame = lock.update();
+    /// frame.iter_mut().for_each(|pixel| *pixel = 2 );
+    /// drop(frame);
+    /// // the old frame is no longer read, so start drawing the next one
+    /// lock.try_reclaim(|frame| frame.iter_mut().for_each(|pixel| *pixel = 3 ) )
+    ///     .unwrap();
+    /// assert_eq!(lock.get_clone(), [2; 4]);
+    /// drop(lock.update());
+    /// assert_eq!(lock.get_clone(), [3; 4]);
+    /// ```
+    pub fn try_reclaim<F:FnOnce(&mut T)->R,R>(&self,  f: F)
+    -> Result<R,TryUpdateError> {
+        let mut guard = self.try_update()?;
+        let result = f(&mut guard);
+        UpdateGuard::cancel(guard);
+        Ok(result)
+    }
+
+    /// Blocks until there are no unfinished reads of the value that is active
+    /// when this method is called.
+    ///
+    /// Useful before tearing down something the current value refers to.
+    /// Reads of a value that was replaced before the call are not waited for.
+    /// If the value stops being active while waiting, its final read count is
+    /// checked with `.try_update()`-style attempts at taking the update lock,
+    /// so an update in progress delays this but doesn't block it.
+    ///
+    /// As new reads can start all the time, this waits for a moment where none
+    /// are in progress, and can therefore block indefinitely if there are
+    /// always reads of the value. Use `.quiesce_timeout()` to bound the wait.
+    pub fn quiesce(&self) {
+        self.quiesce_until(|| false );
+    }
+    /// Like `.quiesce()`, but gives up after `timeout`.
+    ///
+    /// Returns whether there were no longer any unfinished reads.
+    /// Returns `false` at the deadline also if the value has been replaced
+    /// and an update has held the lock the whole time since.
+    /// A timeout too long to be represented as an `Instant` waits like
+    /// `.quiesce()`.
+    #[cfg(feature="std")]
+    pub fn quiesce_timeout(&self,  timeout: Duration) -> bool {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.quiesce_until(|| Instant::now() >= deadline ),
+            None => self.quiesce_until(|| false ),
+        }
+    }
+    /// Waits for no reads of the active value, or until `timed_out()` returns
+    /// true.
+    fn quiesce_until<F:Fn()->bool>(&self,  timed_out: F) -> bool {
+        let slot = self.reads_active.load(SeqCst) & 1;
+        let mut backoff = self.backoff.clone();
+        loop {
+            backoff.reset();
+            loop {
+                if self.no_reads_of(slot) {
+                    return true;
+                }
+                if !backoff.spin() {
+                    break;
+                }
+            }
+            if timed_out() {
+                return false;
+            }
+            yield_now();
+        }
+    }
+    /// Checks whether there was a moment with no reads of `slot` in progress.
+    ///
+    /// Returns false if the slot is inactive and an update holds the lock,
+    /// as that would otherwise block.
+    fn no_reads_of(&self,  slot: usize) -> bool {
+        // Loading the finished count first means that if it equals the started
+        // count loaded afterwards, all reads started before were done.
+        let finished = self.finished_reads[slot].load(SeqCst);
+        let started = self.reads_active.load(SeqCst);
+        if started & 1 == slot {
+            finished == started
+        } else {
+            // the final count of the inactive slot is stored in the mutex
+            match try_lock(&self.inactive_reads) {
+                Some(inactive_reads) => {
+                    *inactive_reads & 1 == slot
+                        && self.finished_reads[slot].load(SeqCst) == *inactive_reads
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// Registers a function to be called with the new active value every time
+    /// an update makes a value active.
+    ///
+    /// The hooks are called by the thread doing the update, after the value
+    /// has become visible to reads but while the update lock is still held.
+    /// They should therefore be quick, and must not update this `PairLock`
+    /// or register another hook on it, as that would deadlock.
+    ///
+    /// Hooks cannot be removed, and are not cloned with the `PairLock`.
+    ///
+    /// If a hook panics the new value stays active and the lock is released.
+    /// But when the update is committed by an `UpdateGuard` that is dropped
+    /// while the thread is already panicking, a panicking hook aborts the
+    /// process, like any panic in a drop impl during unwinding.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::mpsc::channel;
+    /// let lock = PairLock::new(1, 0);
+    /// let (sender, receiver) = channel();
+    /// lock.on_commit(move |v| sender.send(*v).unwrap() );
+    /// lock.set(2);
+    /// assert_eq!(receiver.try_recv(), Ok(2));
+    /// ```
+    pub fn on_commit<F:FnMut(&T)+Send+'static>(&self,  hook: F) {
+        let mut hooks = lock(&self.on_commit);
+        hooks.push(Box::new(hook));
+        // updates that see the flag also see the hook when they lock
+        self.has_hooks.store(true, Relaxed);
+    }
+    /// Sends a clone of the new active value to `tx` every time an update
+    /// makes a value active, for feeding a channel-based consumer.
+    ///
+    /// Every commit results in one send, so values are not coalesced: a slow
+    /// receiver will find every intermediate value queued up.
+    /// Send errors are ignored, so dropping the receiver is fine, but the
+    /// value is still cloned on every commit.
+    ///
+    /// This is an `.on_commit()` hook, and the same restrictions apply.
+    #[cfg(feature="std")]
+    pub fn forward_changes(&self,  tx: Sender<T>) where T: Clone+Send+'static {
+        self.on_commit(move |v| {let _ = tx.send(v.clone());} );
+    }
+    /// Creates a `PairLock` that stores `f` applied to the active value of this
+    /// one, and keeps it updated with an `.on_commit()` hook.
+    ///
+    /// Useful when some readers only care about part of a bigger value.
+    /// The projection is updated after a new value has become active here,
+    /// so readers of both might briefly see the old projection with the new
+    /// value. Updates here also wait for updating the projection, which blocks
+    /// if it has unfinished reads of its inactive value.
+    ///
+    /// The hook only holds a weak reference to the projection, so it stops
+    /// being updated once all `Arc`s to it have been dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let config = PairLock::new((80, "foo"), (0, ""));
+    /// let port = config.project(|&(port, _)| port );
+    /// assert_eq!(port.read(), 80);
+    /// config.set((8080, "foo"));
+    /// assert_eq!(port.read(), 8080);
+    /// ```
+    pub fn project<U,F>(&self,  f: F) -> Arc<PairLock<U>>
+    where U: Clone+Send+Sync+'static, F: Fn(&T)->U+Send+'static {
+        // prevent any update from happening before the hook is registered
+        let _no_updates = lock(&self.inactive_reads);
+        let projection = Arc::new(self.view(|v| PairLock::with_clone(f(v)) ));
+        let weak = Arc::downgrade(&projection);
+        self.on_commit(move |v| {
+            if let Some(projection) = weak.upgrade() {
+                projection.set(f(v));
+            }
+        });
+        projection
+    }
+
+    /// Returns the number of finished reads since the `PairLock` was created.
+    ///
+    /// Every `.view()` counts, which includes `.get_clone()`, `.get()` and
+    /// `.read()`. Reads in progress are not included.
+    ///
+    /// The counters are the ones reads already use, so keeping them has no
+    /// extra cost, but they will wrap around after `usize::MAX` reads. On
+    /// 32-bit platforms that can happen in long-running processes, so compare
+    /// snapshots with `wrapping_sub()`.
+    pub fn total_reads(&self) -> usize {
+        // each read increments by two, and the second slot starts at
+        // SECOND_SLOT_START.
+        let first = self.finished_reads[0].load(Relaxed) / 2;
+        let second = self.finished_reads[1].load(Relaxed)
+            .wrapping_sub(SECOND_SLOT_START) / 2;
+        first.wrapping_add(second)
+    }
+    /// Returns how many reads of the inactive value haven't finished yet,
+    /// which is what `.update()` waits for.
+    ///
+    /// For diagnosing slow readers. It takes the update lock to get the final
+    /// read count of the inactive value, and therefore blocks while an update
+    /// is in progress. Reads can finish right after it returns, so the count
+    /// might already be too high.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// lock.view(|_| {
+    ///     assert_eq!(lock.outstanding_inactive_reads(), 0);
+    ///     let _ = lock.try_update();
+    ///     assert_eq!(lock.outstanding_inactive_reads(), 1);
+    /// });
+    /// assert_eq!(lock.outstanding_inactive_reads(), 0);
+    /// ```
+    pub fn outstanding_inactive_reads(&self) -> usize {
+        let inactive_reads = lock(&self.inactive_reads);
+        let finished = self.finished_reads[*inactive_reads & 1].load(Relaxed);
+        inactive_reads.wrapping_sub(finished) / 2
+    }
+    /// Returns whether an update is in progress, without waiting for it.
+    ///
+    /// Only a hint for sampling contention: the update can finish, or another
+    /// start, right after this returns. It briefly takes the update lock if
+    /// it is free, so a concurrent `.try_update()` might fail because of it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::with_default(0);
+    /// assert!(!lock.is_updating());
+    /// let guard = lock.update();
+    /// assert!(lock.is_updating());
+    /// drop(guard);
+    /// assert!(!lock.is_updating());
+    /// ```
+    pub fn is_updating(&self) -> bool {
+        try_lock(&self.inactive_reads).is_none()
+    }
+    /// Returns the number of updates that have made their value active since
+    /// the `PairLock` was created or `.reset()`.
+    ///
+    /// Includes `.set()`s but not cancelled updates.
+    /// Wraps around after `u32::MAX` updates, as it's the lower half of the
+    /// generation.
+    pub fn total_writes(&self) -> usize {
+        (self.generation.load(Relaxed) % RESET_GENERATION_STEP) as usize
+    }
+    /// Returns the number of updates that have made their value active,
+    /// for detecting whether a snapshot has gone stale.
+    ///
+    /// Incremented after the new value has become active, by dropped
+    /// `UpdateGuard`s, `.set()` and similar, but not by
+    /// `UpdateGuard::cancel()`. It never goes backwards: `.reset()` advances
+    /// it to the next multiple of 2^32.
+    /// A `u64` won't wrap around in practice: at one update per nanosecond it
+    /// takes centuries, so comparing generations for equality or order is
+    /// fine.
+    ///
+    /// Wait-free. A value read after this method returns is at least as new
+    /// as the generation returned, but might be newer.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// let (generation, value) = (lock.generation(), lock.read());
+    /// lock.set(2);
+    /// assert!(lock.generation() > generation);
+    /// # assert_eq!(value, 1);
+    /// ```
+    pub fn generation(&self) -> u64 {
+        // synchronizes with the increment in make_active(), which happens
+        // after the value with this generation has become active.
+        self.generation.load(Acquire)
+    }
+    /// Gives a rough indication of how often updates have had to wait for
+    /// other updates or for reads.
+    ///
+    /// It's the share of `.total_writes()` that came from `.update()` or the
+    /// methods built on it and had to wait before getting the inactive value.
+    /// Cancelled updates are counted by neither, and updates from
+    /// `.try_update()` and the methods with a timeout never count as waiting.
+    ///
+    /// It is meant for adaptive data structures deciding whether a `PairLock`
+    /// is appropriate. It is only advisory, and the thresholds might change.
+    pub fn contention_hint(&self) -> ContentionHint {
+        let writes = self.total_writes();
+        let contended = self.contended_writes.load(Relaxed);
+        if contended <= writes / 8 {
+            ContentionHint::Low
+        } else if contended <= writes / 2 {
+            ContentionHint::Medium
+        } else {
+            ContentionHint::High
+        }
+    }
+    /// Returns how many times `.update()`s have acquired the inactive value
+    /// without waiting, spun, and yielded the thread.
+    ///
+    /// The counters are incremented separately with relaxed ordering, so a
+    /// snapshot taken during updates might not be consistent.
+    /// Only `.update()` and the methods built on it are counted, not
+    /// `.try_update()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::with_default(0);
+    /// lock.set(1);
+    /// assert_eq!(lock.stats().fast_acquisitions, 1);
+    /// ```
+    #[cfg(feature="metrics")]
+    pub fn stats(&self) -> PairLockStats {
+        PairLockStats {
+            fast_acquisitions: self.update_counters.fast_acquisitions.load(Relaxed),
+            spins: self.update_counters.spins.load(Relaxed),
+            yields: self.update_counters.yields.load(Relaxed),
+        }
+    }
+
+    /// Stores a new value in the `PairLock`,
+    /// returning the previously inactive value.
+    ///
+    /// Will block if another update/replace/set is in progress.
+    /// if there are reads of the second last value that haven't finished yet.  
+    ///
+    /// Since `value` is moved in, it can never overlap the slots of the lock,
+    /// so no runtime check for that is needed.
+    /// Storing a reference into the lock itself is rejected by the borrow
+    /// checker, because references passed to `.view()` cannot escape it:
+    ///
+    /// ```compile_fail
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new((0, None), (1, None));
+    /// lock.view(|active| lock.set((2, Some(&active.0))) );
+    /// ```
+    ///
+    /// Raw pointers into the slots can be stored, but will point to whatever
+    /// value later updates put there.
+    pub fn set(&self,  value: T) -> T {
+        mem::replace(&mut*self.update(), value)
+    }
+    /// Like `.set()`, but fails instead of blocking, like `.try_update()`.
+    ///
+    /// # Errors
+    /// Gives back `value` together with the reason, so that it can be retried
+    /// later without requiring `T: Clone`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,TryUpdateError};
+    /// let lock = PairLock::new(1, 0);
+    /// assert_eq!(lock.try_set(2), Ok(0));
+    /// lock.view(|_| {
+    ///     assert_eq!(lock.try_set(3), Ok(1));
+    ///     assert_eq!(lock.try_set(4), Err((4, TryUpdateError::InactiveReads)));
+    /// });
+    /// ```
+    pub fn try_set(&self,  value: T) -> Result<T,(T,TryUpdateError)> {
+        match self.try_update() {
+            Ok(mut guard) => Ok(mem::replace(&mut*guard, value)),
+            Err(error) => Err((value, error)),
+        }
+    }
+    /// Stores `value` if `pred` returns `true` for the active value,
+    /// and otherwise gives it back.
+    ///
+    /// `pred` is called while holding the update lock, so no other update can
+    /// change the active value before `value` is stored.
+    /// Returns the previously inactive value when storing, like `.set()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let version = PairLock::new(2u64, 0);
+    /// assert_eq!(version.set_if(1, |current| 1 > *current ), Err(1));
+    /// assert_eq!(version.set_if(3, |current| 3 > *current ), Ok(0));
+    /// assert_eq!(version.read(), 3);
+    /// ```
+    pub fn set_if<F:FnOnce(&T)->bool>(&self,  value: T,  pred: F) -> Result<T,T> {
+        let mut guard = self.update();
+        if pred(UpdateGuard::active(&guard)) {
+            Ok(mem::replace(&mut*guard, value))
+        } else {
+            UpdateGuard::cancel(guard);
+            Err(value)
+        }
+    }
+    /// Stores the value `f` returns for the active value, or aborts if it
+    /// returns `None`, like `AtomicUsize::fetch_update()`.
+    ///
+    /// Returns a clone of the previous active value in `Ok` if a value was
+    /// stored, and a clone of the unchanged active value in `Err` otherwise.
+    /// Updates are serialized, so unlike with atomics `f` is only called once;
+    /// it takes `FnMut` for symmetry with them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(vec![1], Vec::new());
+    /// let push = |v: &Vec<i32>| if v.len() < 2 {Some([&v[..], &[2]].concat())} else {None};
+    /// assert_eq!(lock.fetch_update(push), Ok(vec![1]));
+    /// assert_eq!(lock.fetch_update(push), Err(vec![1, 2]));
+    /// ```
+    pub fn fetch_update<F:FnMut(&T)->Option<T>>(&self,  mut f: F) -> Result<T,T>
+    where T: Clone {
+        let mut guard = self.update();
+        let active = UpdateGuard::active(&guard);
+        match f(active) {
+            Some(new) => {
+                let previous = active.clone();
+                *guard = new;
+                Ok(previous)
+            }
+            None => {
+                let current = active.clone();
+                UpdateGuard::cancel(guard);
+                Err(current)
+            }
+        }
+    }
+    /// Exchanges the active values of two `PairLock`s, by cloning each into
+    /// the inactive slot of the other.
+    ///
+    /// Both update locks are taken in order of address, so `a.swap_with(&b)`
+    /// and `b.swap_with(&a)` at the same time won't deadlock, but this can
+    /// deadlock with code that holds an `UpdateGuard` of one while updating
+    /// the other. Readers see a consistent value in each lock, but the two
+    /// new values don't become active at exactly the same time.
+    /// The values are cloned with `.clone_from()`, which reuses allocations.
+    ///
+    /// Does nothing if `other` is `self`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let a = PairLock::new(vec![1], Vec::new());
+    /// let b = PairLock::new(vec![2], Vec::new());
+    /// a.swap_with(&b);
+    /// assert_eq!((a.get_clone(), b.get_clone()), (vec![2], vec![1]));
+    /// ```
+    pub fn swap_with(&self,  other: &Self) where T: Clone {
+        if ptr::eq(self, other) {
+            return;
+        }
+        let (mut mine, mut others) = if (self as *const Self) < (other as *const Self) {
+            let mine = self.update();
+            (mine, other.update())
+        } else {
+            let others = other.update();
+            (self.update(), others)
+        };
+        mine.clone_from(UpdateGuard::active(&others));
+        others.clone_from(UpdateGuard::active(&mine));
+    }
+    /// `.set()` which also returns how many times it had to wait.
+    ///
+    /// Waiting for another update counts once, and each check for unfinished
+    /// reads of the inactive value that failed counts once, so the number is
+    /// zero when the update didn't have to wait at all.
+    /// A writer can use it to back off when updates are contended.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(1, 0);
+    /// assert_eq!(lock.set_contended(2), (0, 0));
+    /// ```
+    pub fn set_contended(&self,  value: T) -> (T, usize) {
+        let (mut guard, waits) = self.update_counting();
+        (mem::replace(&mut*guard, value), waits)
+    }
+    /// Makes `T::default()` the active value and returns the previously
+    /// active value, for draining accumulators.
+    ///
+    /// After storing the default as active, this keeps the update lock and
+    /// waits for reads of the previous value to finish before taking it out,
+    /// leaving another default in the now inactive slot.
+    /// The stale value that was in the inactive slot is dropped.
+    ///
+    /// Will block if another update is in progress, and also waits for reads
+    /// of both the inactive and the previously active value.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let metrics = PairLock::new(vec![1, 2], vec![1]);
+    /// assert_eq!(metrics.swap_take(), [1, 2]);
+    /// assert_eq!(metrics.get_clone(), []);
+    /// ```
+    pub fn swap_take(&self) -> T where T: Default {
+        let mut guard = self.update();
+        *guard = T::default();
+        // keeps the lock without committing again when it's released
+        let (mut lock, _) = UpdateGuard::disarm(guard);
+        // without sync, as the value to return would be overwritten
+        lock.make_active(0, None);
+        self.wait_for_inactive_reads(&lock);
+        mem::take(lock.value_mut())
+    }
+    /// Waits, while holding the update lock, until all reads of the inactive
+    /// value have finished.
+    fn wait_for_inactive_reads(&self,  inactive_reads: &usize) {
+        let slot = *inactive_reads & 1;
+        let mut backoff = self.backoff.clone();
+        backoff.reset();
+        let mut yields = 0;
+        while self.finished_reads[slot].load(Relaxed) != *inactive_reads {
+            if backoff.spin() {
+                continue;
+            } else if yields < MAX_UPDATE_YIELDS {
+                yield_now();
+                yields += 1;
+                backoff.reset();
+            } else {
+                self.park_for_reads(inactive_reads);
+            }
+        }
+        fence(self.ordering(Acquire));
+    }
+    /// Parks the thread until a read finishes or `PARK_TIMEOUT` has passed,
+    /// unless all reads of the inactive value have already finished.
+    /// Must only be called while holding the update lock.
+    #[cfg(feature="park")]
+    #[cold]
+    fn park_for_reads(&self,  inactive_reads: &usize) {
+        *lock(&self.parked_writer) = Some(thread::current());
+        // SeqCst to not miss reads that finish before the flag is seen
+        self.waiting.fetch_or(PARKED_WRITER, SeqCst);
+        let slot = *inactive_reads & 1;
+        if self.finished_reads[slot].load(SeqCst) != *inactive_reads {
+            // loom cannot park with a timeout, and a missed wakeup would be
+            // reported as a deadlock
+            #[cfg(loom)]
+            yield_now();
+            #[cfg(not(loom))]
+            park_timeout(PARK_TIMEOUT);
+        }
+        self.waiting.fetch_and(!PARKED_WRITER, Relaxed);
+    }
+    /// Without the `park` feature updates keep yielding instead.
+    #[cfg(all(feature="std", not(feature="park")))]
+    fn park_for_reads(&self,  _: &usize) {
+        yield_now();
+    }
+    /// Without `std` there is nothing to park on, so this only spins once.
+    #[cfg(not(feature="std"))]
+    fn park_for_reads(&self,  _: &usize) {
+        spin_loop();
+    }
+    /// Called by reads that finish while an update is parked or a future is
+    /// waiting.
+    #[cfg(any(feature="park", feature="async"))]
+    #[cold]
+    fn wake_waiting(&self) {
+        // Reads must not block, and a missed wakeup only delays a parked
+        // update until PARK_TIMEOUT. A waiting future is woken by whoever
+        // holds the lock instead, which will either be able to update or
+        // wake it when done.
+        #[cfg(feature="park")]
+        if let Some(parked) = try_lock(&self.parked_writer) {
+            if let Some(writer) = &*parked {
+                writer.unpark();
+            }
+        }
+        #[cfg(feature="async")]
+        if let Some(tasks) = try_lock(&self.tasks) {
+            self.wake_tasks(tasks);
+        }
+    }
+    /// Stores `waker` in `list`, and sets `bit` in `waiting`.
+    #[cfg(feature="async")]
+    fn register_waker(&self,  list: &Mutex<Vec<Waker>>,  bit: usize,  waker: &Waker) {
+        let mut wakers = lock(list);
+        if !wakers.iter().any(|stored| stored.will_wake(waker) ) {
+            wakers.push(waker.clone());
+        }
+        self.waiting.fetch_or(bit, SeqCst);
+        drop(wakers);
+        // make the flag visible before the caller checks again
+        fence(SeqCst);
+    }
+    /// Stores `waker` to be woken when a read finishes or an update releases
+    /// the lock.
+    #[cfg(feature="async")]
+    fn register_task(&self,  waker: &Waker) {
+        self.register_waker(&self.tasks, WAITING_TASKS, waker);
+    }
+    #[cfg(feature="async")]
+    fn wake_all(&self,  mut wakers: MutexGuard<'_,Vec<Waker>>,  bit: usize) {
+        self.waiting.fetch_and(!bit, SeqCst);
+        let woken = mem::take(&mut*wakers);
+        drop(wakers);
+        for waker in woken {
+            waker.wake();
+        }
+    }
+    #[cfg(feature="async")]
+    fn wake_tasks(&self,  tasks: MutexGuard<'_,Vec<Waker>>) {
+        self.wake_all(tasks, WAITING_TASKS);
+    }
+    #[cfg(feature="async")]
+    fn wake_tasks_after_unlock(&self) {
+        // pairs with the fence in register_waker()
+        fence(SeqCst);
+        let waiting = self.waiting.load(Relaxed);
+        if waiting & WAITING_TASKS != 0 {
+            self.wake_tasks(lock(&self.tasks));
+        }
+        if waiting & WATCHERS != 0 {
+            self.wake_all(lock(&self.watchers), WATCHERS);
+        }
+    }
+
+
+    /// Consumes the `PairLock` and returns the active and inactive values.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(true, false);
+    /// let (active, inactive) = lock.into_inner();
+    /// ```
+    pub fn into_inner(self) -> (T, T) {
+        // yay no custom drop impl
+        let PairLock{ reads_active, values, .. } = self;
+        let active = reads_active.into_inner() & 1;
+        // but cannot destructure fixed-size arrays :(
+        unsafe {
+            let active_ = ptr::read(values[active].get());
+            let inactive_ = ptr::read(values[active^1].get());
+            mem::forget(values);
+            (active_, inactive_)
+        }
+    }
+    /// Consumes the `PairLock` and wraps both values in an `Arc`,
+    /// to give readers cheap shared ownership with `.get()`.
+    ///
+    /// Whether clean updates rotate, which orderings are used, the backoff and
+    /// whether commits sync both slots as with `new_synced()` is kept, but
+    /// counters are reset and `.on_commit()` hooks are dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let lock = PairLock::new(String::from("foo"), String::new());
+    /// let shared = lock.into_arc().get();
+    /// assert_eq!(&*shared, "foo");
+    /// ```
+    pub fn into_arc(self) -> PairLock<Arc<T>,B> {
+        let options = self.options;
+        let backoff = self.backoff.clone();
+        // syncing Arcs only clones the pointer
+        #[cfg(feature="synced")]
+        let sync_commits = self.sync_commits
+            .map(|_| (|inactive, active| Arc::clone_from(inactive, active)) as SyncFn<Arc<T>> );
+        let (active, inactive) = self.into_inner();
+        let arcs = PairLock::with_backoff(Arc::new(active), Arc::new(inactive), backoff);
+        PairLock {
+            options,
+            #[cfg(feature="synced")]
+            sync_commits,
+            ..arcs
+        }
+    }
+
+    /// Given exclusive access this method returns mutable references to both
+    /// the active and inactive value.
+    ///
+    /// # Examples
+    /// ```
     /// # use pairlock::PairLock;
     /// let mut lock = PairLock::new(true, false);
     /// let (&mut active, &mut inactive) = lock.get_mut_both();
     /// ```
-    pub fn get_mut_both(&mut self) -> (&mut T, &mut T) {
-        let active = *self.reads_active.get_mut() & 1;
-        unsafe {// safe because &mut self
-            (&mut*self.values[active].get(), &mut*self.values[active^1].get())
-        }
-    }
-    /// Given exclusive access this method returns a mutable reference to
-    /// the active value.
-    pub fn get_mut_active(&mut self) -> &mut T {
-        self.get_mut_both().0
+    pub fn get_mut_both(&mut self) -> (&mut T, &mut T) {
+        // not get_mut() because loom's atomics don't have it
+        let active = self.reads_active.load(Relaxed) & 1;
+        unsafe {// safe because &mut self
+            (&mut*self.values[active].get(), &mut*self.values[active^1].get())
+        }
+    }
+    /// Given exclusive access this method returns a mutable reference to
+    /// the active value.
+    pub fn get_mut_active(&mut self) -> &mut T {
+        self.get_mut_both().0
+    }
+    /// Given exclusive access this method returns a mutable reference to
+    /// the inactive value.
+    pub fn get_mut_inactive(&mut self) -> &mut T {
+        self.get_mut_both().1
+    }
+    /// Given exclusive access this method makes the inactive value a clone
+    /// of the active one.
+    ///
+    /// Uses `.clone_from()`, so the inactive value's allocations can be
+    /// reused.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let mut lock = PairLock::new(vec![1, 2], Vec::new());
+    /// lock.sync_slots();
+    /// let _ = lock.update();
+    /// assert_eq!(lock.get_clone(), [1, 2]);
+    /// ```
+    #[doc(alias = "get_mut_synced")]
+    pub fn sync_slots(&mut self) where T: Clone {
+        let (active, inactive) = self.get_mut_both();
+        inactive.clone_from(active);
+    }
+    /// Given exclusive access this method stores clones of `value` in both
+    /// slots and resets the read counters and commit hooks, making the
+    /// `PairLock` behave like one freshly created by `with_clone()`.
+    ///
+    /// The generation is not restarted but advanced to the next multiple of
+    /// 2^32, so that results cached by `.view_cached()` or generations from
+    /// before are not mistaken for the new value, while `.total_writes()`
+    /// starts at zero again.
+    ///
+    /// Useful for returning a `PairLock` to a pool.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let mut lock = PairLock::with_clone(1);
+    /// let mut cache = None;
+    /// assert_eq!(lock.view_cached(&mut cache, |v| *v ), 1);
+    /// lock.reset(5);
+    /// assert_eq!(lock.view_cached(&mut cache, |v| *v ), 5);
+    /// ```
+    pub fn reset(&mut self,  value: T) where T: Clone {
+        // must match the initial values in new()
+        self.reads_active = AtomicUsize::new(0);
+        self.finished_reads = [AtomicUsize::new(0), AtomicUsize::new(SECOND_SLOT_START)];
+        *get_mut(&mut self.inactive_reads) = SECOND_SLOT_START;
+        // not get_mut() because loom's atomics don't have it
+        let resets = self.generation.load(Relaxed) / RESET_GENERATION_STEP;
+        let generation = (resets + 1) * RESET_GENERATION_STEP;
+        self.generation = AtomicU64::new(generation);
+        self.slot_generations = [AtomicU64::new(generation), AtomicU64::new(generation)];
+        self.contended_writes = AtomicUsize::new(0);
+        #[cfg(feature="metrics")] {
+            self.update_counters = UpdateCounters::default();
+        }
+        get_mut(&mut self.on_commit).clear();
+        self.has_hooks = AtomicBool::new(false);
+        *self.values[0].get_mut() = value.clone();
+        *self.values[1].get_mut() = value;
+    }
+    /// Given exclusive access this method replaces both the active and the
+    /// inactive value, and returns the previous ones.
+    ///
+    /// Useful for reinitializing a `PairLock` that is to be reused.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let mut lock = PairLock::new(1, 2);
+    /// assert_eq!(lock.replace_both(3, 4), (1, 2));
+    /// assert_eq!(lock.read(), 3);
+    /// ```
+    pub fn replace_both(&mut self,  active: T,  inactive: T) -> (T, T) {
+        let (a, i) = self.get_mut_both();
+        (mem::replace(a, active), mem::replace(i, inactive))
+    }
+}
+impl<T:?Sized, B:Backoff> PairLock<Weak<T>,B> {
+    /// Returns the `Arc` the active `Weak` points to, or creates one with
+    /// `init` and stores a `Weak` to it if all `Arc`s have been dropped.
+    ///
+    /// This caches a shared resource for as long as it is in use elsewhere.
+    /// When the resource is alive this is a wait-free view. Otherwise `init`
+    /// is called while holding the update lock, so concurrent callers wait
+    /// for it and then get the same `Arc`, instead of each creating one.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::{Arc, Weak};
+    /// let cache = PairLock::with_clone(Weak::new());
+    /// let a = cache.get_or_init(|| Arc::new(1) );
+    /// let b = cache.get_or_init(|| Arc::new(2) );
+    /// assert!(Arc::ptr_eq(&a, &b));
+    /// drop((a, b));
+    /// assert_eq!(*cache.get_or_init(|| Arc::new(3) ), 3);
+    /// ```
+    pub fn get_or_init<F:FnOnce()->Arc<T>>(&self,  init: F) -> Arc<T> {
+        if let Some(alive) = self.view(Weak::upgrade) {
+            return alive;
+        }
+        let mut guard = self.update();
+        // another caller might have initialized it while this one waited
+        let active = UpdateGuard::active(&guard).clone();
+        if let Some(alive) = active.upgrade() {
+            UpdateGuard::cancel(guard);
+            return alive;
+        }
+        // The inactive Weak could point to an older value that is still alive,
+        // which a panic in init() would make active.
+        *guard = active;
+        let arc = init();
+        *guard = Arc::downgrade(&arc);
+        arc
+    }
+}
+
+impl<T> PairLock<Arc<T>> {
+    /// Puts `value` into an `Arc<T>` and creates a new `PairLock<Arc<T>>`
+    /// with it.
+    pub fn new_arc(value: T) -> Self {
+        PairLock::with_clone(Arc::new(value))
+    }
+}
+impl<T, B:Backoff> PairLock<Arc<T>,B> {
+    /// Unwraps both `Arc`s, the reverse of `.into_arc()`.
+    ///
+    /// Fails and returns the `PairLock` unchanged unless this `PairLock` holds
+    /// the only reference to both values, which is never the case if both
+    /// slots store the same `Arc`, as after `new_arc()`.
+    ///
+    /// Counters are reset and `.on_commit()` hooks are dropped as with
+    /// `.into_arc()`. Unlike `.into_arc()` this also loses the syncing of
+    /// `new_synced()`, because `T` might not implement `Clone`.
+    #[allow(clippy::result_large_err)] // giving back self, like Arc::try_unwrap()
+    pub fn try_into_unshared(mut self) -> Result<PairLock<T,B>, Self> {
+        let (active, inactive) = self.get_mut_both();
+        if Arc::get_mut(active).is_none() || Arc::get_mut(inactive).is_none() {
+            return Err(self);
+        }
+        let options = self.options;
+        let backoff = self.backoff.clone();
+        let (active, inactive) = self.into_inner();
+        let unwrap = |arc| match Arc::try_unwrap(arc) {
+            Ok(value) => value,
+            Err(_) => unreachable!("Arc is not unique after Arc::get_mut() succeeded"),
+        };
+        let values = PairLock::with_backoff(unwrap(active), unwrap(inactive), backoff);
+        Ok(PairLock { options, ..values })
+    }
+}
+impl<T:?Sized, B:Backoff> PairLock<Arc<T>,B> {
+    /// Returns a clone of the active `Arc<T>`.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn get(&self) -> Arc<T> {
+        self.get_clone()
+    }
+    /// Same as `.get()`, for code written for `arc_swap::ArcSwap`.
+    ///
+    /// `ArcSwap::load()` returns a guard, but a cloned `Arc` can be used the
+    /// same way.
+    pub fn load(&self) -> Arc<T> {
+        self.get()
+    }
+    /// Same as `.get()`, for code written for `arc_swap::ArcSwap`.
+    pub fn load_full(&self) -> Arc<T> {
+        self.get()
+    }
+    /// Same as `.set()`, except that the previously inactive `Arc` is dropped,
+    /// for code written for `arc_swap::ArcSwap`.
+    pub fn store(&self,  arc: Arc<T>) {
+        self.set(arc);
+    }
+    /// Calls `f` with a pointer to the value the active `Arc` points to,
+    /// for comparing identity without changing the reference count.
+    ///
+    /// The pointer is only guaranteed to be valid while `f` runs.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::{ptr, sync::Arc};
+    /// let arc = Arc::new(1);
+    /// let lock = PairLock::with_clone(arc.clone());
+    /// assert!(lock.with_ptr(|p| ptr::eq(p, &*arc) ));
+    /// ```
+    pub fn with_ptr<F:FnOnce(*const T)->R,R>(&self,  f: F) -> R {
+        self.view(|arc| f(Arc::as_ptr(arc)) )
+    }
+    /// Replaces the active value with the `Arc` that `f` creates from it, and
+    /// returns the previous one.
+    ///
+    /// This is read-copy-update: `f` is called while holding the update lock,
+    /// so no other update can happen in between, and unlike
+    /// `ArcSwap::rcu()` it is called exactly once.
+    /// `f` should therefore be cheap.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::Arc;
+    /// let lock = PairLock::new_arc(vec![1]);
+    /// let previous = lock.rcu(|v| Arc::new([&v[..], &[2]].concat()) );
+    /// assert_eq!((&*previous, &*lock.get()), (&vec![1], &vec![1, 2]));
+    /// ```
+    pub fn rcu<F:FnOnce(&T)->Arc<T>>(&self,  f: F) -> Arc<T> {
+        let mut guard = self.update();
+        let previous = UpdateGuard::active(&guard).clone();
+        *guard = f(&previous);
+        previous
+    }
+    /// View the value the active `Arc<T>` points to inside a closure.
+    ///
+    /// This avoids both the double dereference of `.view()` and the reference
+    /// count changes of `.get()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::Arc;
+    /// let lock = PairLock::with_clone(Arc::<str>::from("foo"));
+    /// assert_eq!(lock.view_inner(|s: &str| s.len() ), 3);
+    /// ```
+    #[doc(alias = "view_arc")]
+    #[doc(alias = "read_arc")]
+    pub fn view_inner<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> R {
+        self.view(|arc| viewer(arc) )
+    }
+    /// Returns an `Arc` to part of the active value, which `f` typically gets
+    /// by cloning an `Arc` stored in it.
+    ///
+    /// Unlike the reference passed to `.view()`, the returned `Arc` can be
+    /// kept after the read has finished, without cloning the whole value.
+    /// It keeps pointing to the same part after the value is replaced.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::Arc;
+    /// struct Config {name: Arc<str>,  port: u16}
+    /// let lock = PairLock::new_arc(Config{ name: Arc::from("foo"),  port: 80 });
+    /// let name = lock.project_arc(|config| config.name.clone() );
+    /// lock.set(Arc::new(Config{ name: Arc::from("bar"),  port: 80 }));
+    /// assert_eq!(&*name, "foo");
+    /// # let _ = lock.view_inner(|config| config.port );
+    /// ```
+    pub fn project_arc<U:?Sized,F:FnOnce(&Arc<T>)->Arc<U>>(&self,  f: F) -> Arc<U> {
+        self.view(f)
+    }
+    /// Given exclusive access, returns a mutable reference to what the active
+    /// `Arc<T>` points to, if this `PairLock` holds the only reference to it.
+    ///
+    /// Returns `None` if there are other `Arc`s or `Weak`s pointing to the
+    /// value, which includes the inactive slot if it stores a clone of the
+    /// same `Arc`, as `new_arc()` and `with_clone()` do. Replace the inactive
+    /// value through `.get_mut_inactive()` first in that case.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// # use std::sync::Arc;
+    /// let mut lock = PairLock::new_arc(1);
+    /// assert_eq!(lock.get_mut_inner(), None);
+    /// *lock.get_mut_inactive() = Arc::new(0);
+    /// *lock.get_mut_inner().unwrap() = 2;
+    /// assert_eq!(*lock.get(), 2);
+    /// ```
+    pub fn get_mut_inner(&mut self) -> Option<&mut T> {
+        Arc::get_mut(self.get_mut_active())
+    }
+}
+impl<T:Copy, B:Backoff> PairLock<T,B> {
+    /// Returns a copy of the active value.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn read(&self) -> T {
+        self.get_clone()
+    }
+}
+
+#[cfg(feature="serde")]
+impl<T, B:Backoff+Default> PairLock<T,B> {
+    /// Serializes both the active and the inactive value, as a struct with the
+    /// fields `active` and `inactive`.
+    ///
+    /// This is for persisting a `PairLock` with a value staged in the inactive
+    /// slot. It takes the update lock in order to read the inactive value,
+    /// and will therefore block like `.update()` does.
+    /// To serialize only the active value, do it inside `.view()`.
+    ///
+    /// Can be used with `#[serde(serialize_with = "PairLock::serialize_both")]`.
+    pub fn serialize_both<S:serde::Serializer>(&self,  serializer: S)
+    -> Result<S::Ok, S::Error> where T: serde::Serialize {
+        #[derive(serde::Serialize)]
+        struct Both<'a, T:'a> {
+            active: &'a T,
+            inactive: &'a T,
+        }
+        let guard = self.update();
+        let both = Both{ active: UpdateGuard::active(&guard),  inactive: &*guard };
+        let result = serde::Serialize::serialize(&both, serializer);
+        UpdateGuard::cancel(guard);
+        result
+    }
+    /// Deserializes what `.serialize_both()` produced, restoring both values
+    /// to the slots they were in.
+    ///
+    /// Can be used with
+    /// `#[serde(deserialize_with = "PairLock::deserialize_both")]`.
+    pub fn deserialize_both<'de, D:serde::Deserializer<'de>>(deserializer: D)
+    -> Result<Self, D::Error> where T: serde::Deserialize<'de> {
+        #[derive(serde::Deserialize)]
+        struct Both<T> {
+            active: T,
+            inactive: T,
+        }
+        let both: Both<T> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self::with_backoff(both.active, both.inactive, B::default()))
+    }
+}
+
+/// Serializes only the active value.
+///
+/// Use `.serialize_both()` to also include the inactive one.
+#[cfg(feature="serde")]
+impl<T:serde::Serialize, B:Backoff> serde::Serialize for PairLock<T,B> {
+    fn serialize<S:serde::Serializer>(&self,  serializer: S) -> Result<S::Ok, S::Error> {
+        self.view(|v| v.serialize(serializer) )
+    }
+}
+/// Deserializes a single value and clones it into the inactive slot,
+/// like `PairLock::with_clone()`.
+#[cfg(feature="serde")]
+impl<'de, T, B> serde::Deserialize<'de> for PairLock<T,B>
+where T: serde::Deserialize<'de>+Clone,  B: Backoff+Default {
+    fn deserialize<D:serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let active = T::deserialize(deserializer)?;
+        Ok(Self::with_backoff(active.clone(), active, B::default()))
+    }
+}
+
+impl<A, B, X:Backoff> PairLock<(A,B),X> {
+    /// Combines two `PairLock`s into one, so that both parts can be updated
+    /// together.
+    ///
+    /// The active values become the active tuple, and the inactive values the
+    /// inactive one. The combined `PairLock` uses the backoff of `a`, but
+    /// none of the other settings are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let host = PairLock::new("localhost", "");
+    /// let port = PairLock::new(80, 0);
+    /// let addr = PairLock::zip(host, port);
+    /// assert_eq!(addr.read(), ("localhost", 80));
+    /// assert_eq!(addr.set(("example.com", 8080)), ("", 0));
+    /// ```
+    pub fn zip<Y:Backoff>(a: PairLock<A,X>,  b: PairLock<B,Y>) -> Self {
+        let backoff = a.backoff.clone();
+        let (a_active, a_inactive) = a.into_inner();
+        let (b_active, b_inactive) = b.into_inner();
+        Self::with_backoff((a_active, b_active), (a_inactive, b_inactive), backoff)
     }
-    /// Given exclusive access this method returns a mutable reference to
-    /// the inactive value.
-    pub fn get_mut_inactive(&mut self) -> &mut T {
-        self.get_mut_both().1
+}
+
+impl<T> PairLock<Vec<T>> {
+    /// Creates a new `PairLock` with two empty `Vec`s that have room for `cap`
+    /// elements.
+    ///
+    /// This avoids reallocations in the first updates when the inactive `Vec`
+    /// is reused with `clone_from()` or `clear()` and `extend()`.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self::with_capacity_and_backoff(cap, FixedSpins::default())
     }
 }
-impl<T> PairLock<Arc<T>> {
-    /// Puts `value` into an `Arc<T>` and creates a new `PairLock<Arc<T>>`
-    /// with it.
-    pub fn new_arc(value: T) -> Self {
-        PairLock::with_clone(Arc::new(value))
+impl<T, B:Backoff> PairLock<Vec<T>,B> {
+    /// Like `with_capacity()`, but the updates use `backoff` when waiting.
+    pub fn with_capacity_and_backoff(cap: usize,  backoff: B) -> Self {
+        Self::with_backoff(Vec::with_capacity(cap), Vec::with_capacity(cap), backoff)
+    }
+    /// Makes an empty `Vec` active and returns the previous contents,
+    /// for draining a buffer.
+    ///
+    /// This is `.swap_take()`, so both slots are empty afterwards.
+    pub fn take(&self) -> Vec<T> {
+        self.swap_take()
+    }
+    /// Appends `item` to the active `Vec` and keeps the inactive one equal to
+    /// it, so that the next update doesn't start out with a stale `Vec`.
+    ///
+    /// The inactive `Vec` is first overwritten with a clone of the active one
+    /// using `clone_from()`, which reuses its allocation but clones every
+    /// element. A clone of `item` is pushed to it, and it's made active.
+    /// After waiting for reads of the previously active `Vec`, `item` is
+    /// pushed to that one as well.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::PairLock;
+    /// let events = PairLock::<Vec<&str>>::with_capacity(16);
+    /// events.push("started");
+    /// events.push("stopped");
+    /// assert_eq!(events.get_clone(), ["started", "stopped"]);
+    /// assert_eq!(events.take(), ["started", "stopped"]);
+    /// events.push("restarted");
+    /// assert_eq!(events.into_inner(), (vec!["restarted"], vec!["restarted"]));
+    /// ```
+    pub fn push(&self,  item: T) where T: Clone {
+        let mut guard = self.update();
+        let (inactive, active) = UpdateGuard::both(&mut guard);
+        inactive.clone_from(active);
+        inactive.push(item.clone());
+        // keeps the lock without committing again when it's released
+        let (mut lock, _) = UpdateGuard::disarm(guard);
+        // without sync, as that would already add the item to the old Vec
+        lock.make_active(0, None);
+        self.wait_for_inactive_reads(&lock);
+        // the lock now refers to the previously active Vec
+        lock.value_mut().push(item);
     }
 }
-impl<T:?Sized> PairLock<Arc<T>> {
-    /// Returns a clone of the active `Arc<T>`.
+impl<T, B:Backoff> PairLock<Box<[T]>,B> {
+    /// Lets `f` overwrite the inactive slice and then makes it active,
+    /// for double-buffering batches of fixed capacity.
     ///
-    /// Will never block in any way, and should run in constant time.
-    pub fn get(&self) -> Arc<T> {
-        self.get_clone()
+    /// `f` returns how many elements it wrote, which is passed through.
+    /// The slices are never resized, so after a partial fill the rest of the
+    /// active slice contains elements from two writes ago. If readers need
+    /// to know the length of the batch it must be stored in the elements
+    /// (such as with `Option<T>`) or the lock should be a
+    /// `PairLock<(usize, Box<[T]>)>` instead.
+    ///
+    /// # Panics
+    ///
+    /// If `f` returns a number greater than the length of the slice.
+    pub fn fill<F:FnOnce(&mut [T])->usize>(&self,  f: F) -> usize {
+        let mut guard = self.update();
+        let written = f(&mut guard);
+        assert!(written <= guard.len(), "fill() wrote more elements than the slice has room for");
+        written
     }
 }
-impl<T:Copy> PairLock<T> {
-    /// Returns a copy of the active value.
+impl PairLock<String> {
+    /// Creates a new `PairLock` with two empty `String`s that have room for
+    /// `cap` bytes.
     ///
-    /// Will never block in any way, and should run in constant time.
-    pub fn read(&self) -> T {
-        self.get_clone()
+    /// This avoids reallocations in the first updates when the inactive
+    /// `String` is reused with `clone_from()` or `clear()` and `push_str()`.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self::with_capacity_and_backoff(cap, FixedSpins::default())
+    }
+}
+impl<B:Backoff> PairLock<String,B> {
+    /// Like `with_capacity()`, but the updates use `backoff` when waiting.
+    pub fn with_capacity_and_backoff(cap: usize,  backoff: B) -> Self {
+        Self::with_backoff(String::with_capacity(cap), String::with_capacity(cap), backoff)
     }
 }
 
-impl<T:Debug> Debug for PairLock<T> {
+impl<T:Debug, B:Backoff> Debug for PairLock<T,B> {
     fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
         struct Hidden;
         impl Debug for Hidden {
@@ -328,18 +2404,66 @@ impl<T:Debug> Debug for PairLock<T> {
     }
 }
 
+/// Compares the active values.
+///
+/// Both locks are read at the same time, which cannot deadlock because reads
+/// never wait. The result is only a snapshot: either lock can be updated
+/// before the comparison returns.
+impl<T:PartialEq, B:Backoff> PartialEq for PairLock<T,B> {
+    fn eq(&self,  other: &Self) -> bool {
+        self.view(|a| other.view(|b| a == b ) )
+    }
+}
+impl<T:Eq, B:Backoff> Eq for PairLock<T,B> {}
+/// Hashes the active value.
+///
+/// Like `PartialEq`, this is a snapshot, so the hash of a `PairLock` that is
+/// updated while stored in a `HashMap` or `HashSet` will no longer match.
+impl<T:Hash, B:Backoff> Hash for PairLock<T,B> {
+    fn hash<H:Hasher>(&self,  state: &mut H) {
+        self.view(|v| v.hash(state) )
+    }
+}
+
+/// Formats the active value.
+impl<T:Display, B:Backoff> Display for PairLock<T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        self.view(|v| Display::fmt(v, fmtr) )
+    }
+}
+
 impl<T:Default> Default for PairLock<T> {
     fn default() -> Self {
         Self::new(T::default(), T::default())
     }
 }
 
-impl<T:Clone> Clone for PairLock<T> {
+/// Same as `PairLock::with_clone()`.
+impl<T:Clone> From<T> for PairLock<T> {
+    fn from(init: T) -> Self {
+        Self::with_clone(init)
+    }
+}
+/// Same as `PairLock::new()`: the first element becomes the active value,
+/// and the second the inactive one.
+impl<T> From<(T,T)> for PairLock<T> {
+    fn from((active, inactive): (T,T)) -> Self {
+        Self::new(active, inactive)
+    }
+}
+
+impl<T:Clone, B:Backoff> Clone for PairLock<T,B> {
     /// Returns a new `PairLock` initialized with the current `Arc` in `self`.
     ///
     /// Does not clone the content of the `Arc`.
     fn clone(&self) -> Self {
-        Self::new(self.get_clone(), self.get_clone())
+        let clone = Self::with_backoff(self.get_clone(), self.get_clone(), self.backoff.clone());
+        PairLock {
+            options: self.options,
+            #[cfg(feature="synced")]
+            sync_commits: self.sync_commits,
+            ..clone
+        }
     }
     fn clone_from(&mut self,  source: &Self) {
         let (a,b) = unsafe {
@@ -353,62 +2477,371 @@ impl<T:Clone> Clone for PairLock<T> {
 }
 
 
+/// Views the active values of two `PairLock`s at the same time.
+///
+/// The locks are independent, so an update of `b` can become active after
+/// `a` was read but before `b` is, and the pair of values might never have
+/// been active at the same time. Both reads are finished when `f` returns
+/// or panics.
+///
+/// # Examples
+/// ```
+/// # use pairlock::{PairLock,view_two};
+/// let hosts = PairLock::with_clone(vec!["a", "b"]);
+/// let port = PairLock::with_clone(80);
+/// let addresses = view_two(&hosts, &port, |hosts, port| {
+///     hosts.iter().map(|host| format!("{}:{}", host, port) ).collect::<Vec<_>>()
+/// });
+/// assert_eq!(addresses, ["a:80", "b:80"]);
+/// ```
+pub fn view_two<A, B, R, F, X, Y>(a: &PairLock<A,X>,  b: &PairLock<B,Y>,  f: F) -> R
+where F: FnOnce(&A,&B)->R, X: Backoff, Y: Backoff {
+    a.view(|a| b.view(|b| f(a, b) ) )
+}
+
+
+/// Creates `PairLock`s with non-default settings.
+///
+/// # Examples
+/// ```
+/// # use pairlock::{PairLockBuilder,ExponentialBackoff};
+/// let lock = PairLockBuilder::new().spins(100).build(1, 0);
+/// lock.set(2);
+/// let lock = PairLockBuilder::new().backoff(ExponentialBackoff::new(4)).build(1, 0);
+/// lock.set(2);
+/// ```
+pub struct PairLockBuilder<T, B=FixedSpins> {
+    backoff: B,
+    _value: PhantomData<fn()->T>,
+}
+impl<T> PairLockBuilder<T> {
+    /// Creates a builder that by default creates `PairLock`s identical to
+    /// those from `PairLock::new()`.
+    pub fn new() -> Self {
+        PairLockBuilder { backoff: FixedSpins::default(),  _value: PhantomData }
+    }
+    /// Sets how many times an update checks for unfinished reads of the
+    /// inactive value before yielding the thread. The default is 7.
+    ///
+    /// Higher values can reduce latency of updates when reads are short,
+    /// at the cost of CPU usage. Zero is treated as one, because an update
+    /// always checks at least once.
+    pub fn spins(mut self,  spins: usize) -> Self {
+        self.backoff = FixedSpins::new(spins);
+        self
+    }
+}
+impl<T, B:Backoff> PairLockBuilder<T,B> {
+    /// Makes updates and other methods that wait use `backoff` to decide
+    /// between spinning and yielding, replacing any `.spins()`.
+    pub fn backoff<C:Backoff>(self,  backoff: C) -> PairLockBuilder<T,C> {
+        PairLockBuilder { backoff,  _value: PhantomData }
+    }
+    /// Creates a `PairLock` with the chosen settings.
+    pub fn build(self,  active: T,  inactive: T) -> PairLock<T,B> {
+        PairLock::with_backoff(active, inactive, self.backoff)
+    }
+}
+impl<T> Default for PairLockBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T, B:Debug> Debug for PairLockBuilder<T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("PairLockBuilder")
+            .field("backoff", &self.backoff)
+            .finish()
+    }
+}
+
+/// Types that store an `Arc<T>` which can be loaded and replaced from
+/// multiple threads, for code that is generic over which one is used.
+///
+/// Implemented by `PairLock<Arc<T>>` and `RingLock<Arc<T>, N>`.
+/// It is object safe, so `&dyn Shared<T>` also works.
+///
+/// # Examples
+/// ```
+/// # use pairlock::{PairLock,RingLock,Shared};
+/// # use std::sync::Arc;
+/// fn bump(counter: &dyn Shared<u32>) {
+///     let current = counter.load();
+///     counter.store(Arc::new(*current + 1));
+/// }
+/// let pair = PairLock::new_arc(1);
+/// let ring = RingLock::<_,3>::with_clone(Arc::new(1));
+/// bump(&pair);
+/// bump(&ring);
+/// assert_eq!((*pair.get(), *ring.get_clone()), (2, 2));
+/// ```
+pub trait Shared<T:?Sized> {
+    /// Returns a clone of the current `Arc`.
+    fn load(&self) -> Arc<T>;
+    /// Replaces the current `Arc`.
+    fn store(&self,  arc: Arc<T>);
+}
+impl<T:?Sized, B:Backoff> Shared<T> for PairLock<Arc<T>,B> {
+    fn load(&self) -> Arc<T> {
+        self.get()
+    }
+    fn store(&self,  arc: Arc<T>) {
+        self.set(arc);
+    }
+}
+impl<T:?Sized, const N: usize> Shared<T> for RingLock<Arc<T>,N> {
+    fn load(&self) -> Arc<T> {
+        self.get_clone()
+    }
+    fn store(&self,  arc: Arc<T>) {
+        self.set(arc);
+    }
+}
+
+/// Decides how long methods that wait for reads or other updates spin
+/// before yielding the thread.
+///
+/// Each `PairLock` stores one, which is cloned every time a method needs to
+/// wait.
+pub trait Backoff: Clone {
+    /// Prepares for a new round of spinning.
+    /// Called before the first check, and after each yield.
+    fn reset(&mut self);
+    /// Called after a check failed. Waits a little and returns `true` to check
+    /// again, or returns `false` to yield the thread before the next round.
+    fn spin(&mut self) -> bool;
+}
+
+/// Checks a fixed number of times, with a `spin_loop()` hint between each
+/// check, before yielding.
+///
+/// This is the default, with 7 checks.
+#[derive(Clone,Copy, PartialEq,Eq, Debug)]
+pub struct FixedSpins {
+    checks: u32,
+    done: u32,
+}
+impl FixedSpins {
+    /// Zero is treated as one, because there is always at least one check,
+    /// and counts above `u32::MAX` are capped to it.
+    pub fn new(checks: usize) -> Self {
+        let checks = u32::try_from(checks).unwrap_or(u32::MAX);
+        FixedSpins { checks: checks.max(1),  done: 0 }
+    }
+}
+impl Default for FixedSpins {
+    fn default() -> Self {
+        Self::new(DEFAULT_UPDATE_SPINS)
+    }
+}
+impl Backoff for FixedSpins {
+    fn reset(&mut self) {
+        self.done = 0;
+    }
+    fn spin(&mut self) -> bool {
+        self.done += 1;
+        if self.done >= self.checks {
+            return false;
+        }
+        spin_loop();
+        true
+    }
+}
+
+/// Doubles the number of `spin_loop()` hints between each check, and yields
+/// after `max_step` rounds of that.
+///
+/// The default is six steps, which spins at most 63 times between yields.
+#[derive(Clone,Copy, PartialEq,Eq, Debug)]
+pub struct ExponentialBackoff {
+    max_step: u32,
+    step: u32,
+}
+impl ExponentialBackoff {
+    /// `max_step` is capped at 16.
+    pub fn new(max_step: u32) -> Self {
+        ExponentialBackoff { max_step: max_step.min(16),  step: 0 }
+    }
+}
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+impl Backoff for ExponentialBackoff {
+    fn reset(&mut self) {
+        self.step = 0;
+    }
+    fn spin(&mut self) -> bool {
+        if self.step >= self.max_step {
+            return false;
+        }
+        for _ in 0..1u32 << self.step {
+            spin_loop();
+        }
+        self.step += 1;
+        true
+    }
+}
+
 /// A RAII guard providing mutable access to the inactive value of a `PairLock`,
 /// The values becomes active when the guard is dropped.
-pub struct UpdateGuard<'a, T:'a> {
-    guard: MutexGuard<'a, usize>,
-    pl: &'a PairLock<T>,
+///
+/// It can also be used to stage a value without publishing it: The thread
+/// holding the guard can preview the staged value through `Deref`, and
+/// `UpdateGuard::cancel()` keeps it in the inactive slot for the next update.
+/// Nobody else can see the inactive value, as it might be in the middle of
+/// being modified.
+pub struct UpdateGuard<'a, T:'a, B:'a+Backoff=FixedSpins> {
+    lock: UpdateLock<'a,T,B>,
+    /// Set when a mutable reference has been handed out.
+    dirty: bool,
+    /// Copies the new active value into the inactive slot after committing.
+    sync_on_commit: Option<fn(&mut T, &T)>,
 }
-impl<'a,T> Drop for UpdateGuard<'a,T> {
+impl<'a, T, B:Backoff> Drop for UpdateGuard<'a,T,B> {
     /// Makes the value active and releases the update lock
     fn drop(&mut self) {
-        let inactive_reads = *self.guard;
-        fence(SeqCst);
-        // makes the new value active
-        let active_reads = self.pl.reads_active.swap(inactive_reads, SeqCst);
-        *self.guard = active_reads;
-        // and the mutex guard is dropped by the compiler
+        if self.dirty || self.lock.pl.options.rotate_clean() {
+            self.lock.make_active(0, self.sync_on_commit);
+        }
+        // the lock is released when the field is dropped afterwards,
+        // which also happens if a commit hook or sync panics
     }
 }
 // I assume these methods are not called many times per instance,
 // and have therefero optimized for struct size.
-impl<'a,T> Deref for UpdateGuard<'a,T> {
+impl<'a, T, B:Backoff> Deref for UpdateGuard<'a,T,B> {
     type Target = T;
     fn deref(&self) -> &T {
         unsafe {
-            let slot = *self.guard & 1;
-            &*self.pl.values[slot].get()
+            let slot = **self.lock & 1;
+            &*self.lock.pl.values[slot].get()
         }
     }
 }
-impl<'a,T> DerefMut for UpdateGuard<'a,T> {
+impl<'a, T, B:Backoff> DerefMut for UpdateGuard<'a,T,B> {
     fn deref_mut(&mut self) -> &mut T {
+        self.dirty = true;
         unsafe {
-            let slot = *self.guard & 1;
-            &mut*self.pl.values[slot].get()
+            let slot = **self.lock & 1;
+            &mut*self.lock.pl.values[slot].get()
         }
     }
 }
-impl<'a,T> UpdateGuard<'a,T> {
+impl<'a, T, B:Backoff> UpdateGuard<'a,T,B> {
     /// Returns a shared reference to the active value of the `PairLock`.
     ///
     /// It can not be mutate it while the `PairLock` is locked, and is therefore
     /// safe to read.
     pub fn active(this: &Self) -> &T {
         unsafe {
-            let other_slot = (!*this.guard) & 1;
-            &*this.pl.values[other_slot].get()
+            let other_slot = (!**this.lock) & 1;
+            &*this.lock.pl.values[other_slot].get()
         }
     }
     /// Returns references to both the inactive (mutable) and active value of
     /// the `PairLock`.
     pub fn both(this: &mut Self) -> (&mut T, &T) {
+        this.dirty = true;
         unsafe {
-            let slot = *this.guard & 1;
-            let values = &this.pl.values;
+            let slot = **this.lock & 1;
+            let values = &this.lock.pl.values;
             (&mut*values[slot].get(), &*values[slot^1].get())
         }
     }
+    /// Stores `value` as the mutable value, and makes the commit also store a
+    /// clone of it in the other slot, so that both slots are equal afterwards.
+    ///
+    /// Normally the previously active value becomes the inactive one, which
+    /// the next update will find in its guard. Here the commit instead waits
+    /// for reads of the previously active value to finish, while still holding
+    /// the lock, and then overwrites it with `.clone_from()`.
+    /// The next update therefore starts out with the current value, at the
+    /// cost of a slower commit.
+    ///
+    /// Cancelling the guard discards this, but keeps `value` in the inactive
+    /// slot.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::new(vec![1], vec![0]);
+    /// let mut guard = lock.update();
+    /// UpdateGuard::set_both(&mut guard, vec![2]);
+    /// drop(guard);
+    /// assert_eq!(*lock.update(), [2]);
+    /// ```
+    pub fn set_both(this: &mut Self,  value: T) where T: Clone {
+        **this = value;
+        this.sync_on_commit = Some(|inactive, active| inactive.clone_from(active) );
+    }
+    /// Replaces the mutable value with a clone of the active one, and makes the
+    /// commit store a clone of the result in the other slot, like
+    /// `set_both()`.
+    ///
+    /// This gives the semantics of a normal `RwLock`: the guard starts out
+    /// with the current value, and the next update will too. Without it the
+    /// guard contains the value from before the previous update.
+    /// The costs are two clones and a commit that waits for reads of the
+    /// previous value.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::new(vec![1], vec![0]);
+    /// let mut guard = lock.update();
+    /// UpdateGuard::make_both_equal(&mut guard);
+    /// guard.push(2);
+    /// drop(guard);
+    /// assert_eq!(*lock.update(), [1, 2]);
+    /// assert_eq!(lock.get_clone(), [1, 2]);
+    /// ```
+    pub fn make_both_equal(this: &mut Self) where T: Clone {
+        let (inactive, active) = Self::both(this);
+        inactive.clone_from(active);
+        this.sync_on_commit = Some(|inactive, active| inactive.clone_from(active) );
+    }
+    /// Returns the index (0 or 1) of the slot the mutable value is stored in.
+    ///
+    /// This is for diagnostics and white-box tests of double-buffering built
+    /// on top of `PairLock`: the index alternates between updates and has no
+    /// other meaning.
+    pub fn slot(this: &Self) -> usize {
+        **this.lock & 1
+    }
+    /// Makes the mutable value active and releases the lock, like dropping the
+    /// guard does, but reads better than `drop(guard)`.
+    ///
+    /// This makes the value active even if the `PairLock` was created with
+    /// `new_no_rotate_on_clean()` and the guard is clean.
+    ///
+    /// Returns the previously active value: the lock is kept until reads of
+    /// it have finished, and it's then replaced by a clone of the new value,
+    /// like `PairLock.swap_take()` does with `T::default()`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::new(1, 0);
+    /// let mut guard = lock.update();
+    /// *guard = 2;
+    /// assert_eq!(UpdateGuard::commit(guard), 1);
+    /// assert_eq!(lock.read(), 2);
+    /// assert_eq!(*lock.update(), 2);
+    /// ```
+    pub fn commit(this: Self) -> T where T: Clone {
+        let (mut lock, sync_on_commit) = Self::disarm(this);
+        // without sync, as the value to return would be overwritten
+        lock.make_active(0, None);
+        lock.pl.wait_for_inactive_reads(&lock);
+        let active = unsafe { &*lock.pl.values[(!**lock) & 1].get() };
+        let previous = mem::replace(lock.value_mut(), active.clone());
+        if let Some(sync) = sync_on_commit {
+            sync(lock.value_mut(), active);
+        }
+        previous
+    }
     /// Aborts the update by releasing the lock without making the mutable value
     /// active.
     /// 
@@ -416,23 +2849,121 @@ impl<'a,T> UpdateGuard<'a,T> {
     /// next `.update()` or `.replace()`.
     pub fn cancel(this: Self) {
         // unlock the mutex without changing reads_active or inactive_reads 
-        unsafe {
-            // forget self first in case the MutexGuard drop impl can unwind
-            let guard = ptr::read(&this.guard);
-            mem::forget(this);
-            drop(guard);
+        drop(Self::disarm(this));
+    }
+    /// Makes the mutable value active and then views it, without letting any
+    /// other update in between.
+    ///
+    /// The viewer is guaranteed to see the value from this update, even if
+    /// other updates are waiting. Later updates can complete while the viewer
+    /// runs, but the next one after that will wait for it to return.
+    ///
+    /// This makes the value active even if the `PairLock` was created with
+    /// `new_no_rotate_on_clean()` and the guard is clean.
+    /// It takes a closure for the same reason `.view()` does.
+    ///
+    /// # Examples
+    /// ```
+    /// # use pairlock::{PairLock,UpdateGuard};
+    /// let lock = PairLock::new(1, 0);
+    /// let mut guard = lock.update();
+    /// *guard = 2;
+    /// assert_eq!(UpdateGuard::commit_and_view(guard, |v| *v ), 2);
+    /// assert_eq!(lock.read(), 2);
+    /// ```
+    pub fn commit_and_view<F:FnOnce(&T)->R,R>(this: Self,  viewer: F) -> R {
+        let pl = this.lock.pl;
+        let slot = **this.lock & 1;
+        let (mut lock, sync_on_commit) = Self::disarm(this);
+        // start the read as part of making the value active,
+        // and finish it even if a commit hook panics
+        let _defer = Releaser(pl, slot);
+        lock.make_active(2, sync_on_commit);
+        drop(lock);
+        viewer(unsafe { &*pl.values[slot].get() })
+    }
+    /// Takes the lock out of the guard without running the drop impl.
+    fn disarm(this: Self) -> (UpdateLock<'a,T,B>, Option<SyncFn<T>>) {
+        let this = ManuallyDrop::new(this);
+        // the guard is forgotten, so the lock is only dropped once
+        (unsafe { ptr::read(&this.lock) }, this.sync_on_commit)
+    }
+}
+
+/// The locked mutex of an update, which wakes waiting futures after it has
+/// been released.
+struct UpdateLock<'a, T:'a, B:'a+Backoff> {
+    /// Dropped manually, so that futures can be woken after it's unlocked.
+    guard: ManuallyDrop<MutexGuard<'a, usize>>,
+    pl: &'a PairLock<T,B>,
+    /// Whether `.update()` had to wait for it, counted when committing.
+    contended: bool,
+}
+impl<'a, T, B:Backoff> Drop for UpdateLock<'a,T,B> {
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        #[cfg(feature="async")]
+        self.pl.wake_tasks_after_unlock();
+    }
+}
+impl<'a, T, B:Backoff> Deref for UpdateLock<'a,T,B> {
+    type Target = MutexGuard<'a, usize>;
+    fn deref(&self) -> &MutexGuard<'a, usize> {
+        &self.guard
+    }
+}
+impl<'a, T, B:Backoff> DerefMut for UpdateLock<'a,T,B> {
+    fn deref_mut(&mut self) -> &mut MutexGuard<'a, usize> {
+        &mut self.guard
+    }
+}
+impl<'a, T, B:Backoff> UpdateLock<'a,T,B> {
+    /// Returns the value in the slot that is inactive while the lock is held.
+    fn value_mut(&mut self) -> &mut T {
+        unsafe { &mut*self.pl.values[**self.guard & 1].get() }
+    }
+
+    /// Makes the mutable value active, with `started_reads` (which must be
+    /// even) already registered for it.
+    fn make_active(&mut self,  started_reads: usize,  sync_on_commit: Option<SyncFn<T>>) {
+        let inactive_reads = **self.guard;
+        // the generation only changes while holding the lock
+        let generation = self.pl.generation.load(Relaxed) + 1;
+        self.pl.slot_generations[inactive_reads & 1].store(generation, Relaxed);
+        fence(self.pl.ordering(Release));
+        // makes the new value active
+        let new_reads = inactive_reads.wrapping_add(started_reads);
+        let active_reads = self.pl.reads_active.swap(new_reads, self.pl.ordering(AcqRel));
+        **self.guard = active_reads;
+        // Release lets generation() readers rely on seeing the new value
+        // after seeing the new count.
+        self.pl.generation.fetch_add(1, Release);
+        if self.contended {
+            self.pl.contended_writes.fetch_add(1, Relaxed);
+        }
+        // the new active value cannot be modified while the lock is held
+        let active = unsafe { &*self.pl.values[inactive_reads & 1].get() };
+        if self.pl.has_hooks.load(Relaxed) {
+            for hook in lock(&self.pl.on_commit).iter_mut() {
+                hook(active);
+            }
+        }
+        if let Some(sync) = sync_on_commit {
+            self.pl.wait_for_inactive_reads(&self.guard);
+            let inactive = unsafe { &mut*self.pl.values[active_reads & 1].get() };
+            sync(inactive, active);
         }
     }
 }
-impl<'a, T:Debug> Debug for UpdateGuard<'a,T> {
+impl<'a, T:Debug, B:Backoff> Debug for UpdateGuard<'a,T,B> {
     fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
         fmtr.debug_struct("UpdateGuard")
-            .field("mutable", &*self)
-            .field("active", &*UpdateGuard::active(self))
+            .field("mutable", &**self)
+            .field("active", Self::active(self))
             .finish()
     }
 }
-impl<'a,T> PartialEq for UpdateGuard<'a,T> {
+impl<'a, T, B:Backoff> PartialEq for UpdateGuard<'a,T,B> {
     /// Convenience impl for comparing `Result`s containing this type.
     ///
     /// Only compares equal against itself (`ptr::eq(self, other)`).
@@ -442,27 +2973,202 @@ impl<'a,T> PartialEq for UpdateGuard<'a,T> {
 }
 
 
+/// A reference to the active value of a `PairLock` that is only valid inside
+/// the closure passed to `PairLock.read_scope()`.
+pub struct ReadRef<'a, T:'a>(&'a T);
+
+impl<'a,T> Deref for ReadRef<'a,T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+impl<'a, T:Debug> Debug for ReadRef<'a,T> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.0, fmtr)
+    }
+}
+
+/// A read of a `PairLock` that lasts until this guard is dropped,
+/// created by `PairLock.guard_read()`.
+#[cfg(feature="read-guard")]
+pub struct ReadGuard<'a, T:'a, B:'a+Backoff=FixedSpins> {
+    value: &'a T,
+    _releaser: Releaser<'a,T,B>,
+}
+#[cfg(feature="read-guard")]
+impl<'a, T, B:Backoff> Deref for ReadGuard<'a,T,B> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+#[cfg(feature="read-guard")]
+impl<'a, T:Debug, B:Backoff> Debug for ReadGuard<'a,T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.value, fmtr)
+    }
+}
+
+
+/// Future returned by `PairLock.update_async()`.
+#[cfg(feature="async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct UpdateFuture<'a, T:'a, B:'a+Backoff=FixedSpins> {
+    pl: &'a PairLock<T,B>,
+}
+#[cfg(feature="async")]
+impl<'a, T, B:Backoff> Future for UpdateFuture<'a,T,B> {
+    type Output = UpdateGuard<'a,T,B>;
+    fn poll(self: Pin<&mut Self>,  cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let pl = self.pl;
+        if let Ok(guard) = pl.try_update() {
+            return Poll::Ready(guard);
+        }
+        pl.register_task(cx.waker());
+        // whatever made it fail might have finished before the registration
+        match pl.try_update() {
+            Ok(guard) => Poll::Ready(guard),
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+#[cfg(feature="async")]
+impl<'a, T, B:Backoff> Debug for UpdateFuture<'a,T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_str("UpdateFuture")
+    }
+}
+
+/// Waits for the value of a `PairLock` to change,
+/// created by `PairLock.watch()`.
+#[cfg(feature="async")]
+pub struct Watcher<'a, T:'a+Clone, B:'a+Backoff=FixedSpins> {
+    pl: &'a PairLock<T,B>,
+    /// The generation of the last value returned.
+    seen: Option<u64>,
+}
+#[cfg(feature="async")]
+impl<'a, T:Clone, B:Backoff> Watcher<'a,T,B> {
+    /// Returns a clone of the active value if it has changed since the last
+    /// call, and otherwise waits until an update has made a new value active.
+    ///
+    /// The first call returns immediately. An update that happens while the
+    /// value is being cloned can cause the next call to return the same value
+    /// again, but a change is never missed.
+    pub fn recv(&mut self) -> Recv<'_,'a,T,B> {
+        Recv { watcher: self }
+    }
+}
+#[cfg(feature="async")]
+impl<'a, T:Clone, B:Backoff> Debug for Watcher<'a,T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("Watcher")
+            .field("seen", &self.seen)
+            .finish()
+    }
+}
+
+/// Future returned by `Watcher.recv()`.
+#[cfg(feature="async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct Recv<'w, 'a:'w, T:'a+Clone, B:'a+Backoff=FixedSpins> {
+    watcher: &'w mut Watcher<'a,T,B>,
+}
+#[cfg(feature="async")]
+impl<'w, 'a, T:Clone, B:Backoff> Future for Recv<'w,'a,T,B> {
+    type Output = T;
+    fn poll(mut self: Pin<&mut Self>,  cx: &mut Context<'_>) -> Poll<T> {
+        let watcher = &mut*self.watcher;
+        let pl = watcher.pl;
+        let mut generation = pl.generation();
+        if watcher.seen == Some(generation) {
+            pl.register_waker(&pl.watchers, WATCHERS, cx.waker());
+            // an update might have finished before the registration
+            generation = pl.generation();
+            if watcher.seen == Some(generation) {
+                return Poll::Pending;
+            }
+        }
+        watcher.seen = Some(generation);
+        Poll::Ready(pl.get_clone())
+    }
+}
+#[cfg(feature="async")]
+impl<'w, 'a, T:Clone, B:Backoff> Debug for Recv<'w,'a,T,B> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.write_str("Recv")
+    }
+}
+
+/// What happened in the waiting loop of `.update()`,
+/// counted with the `metrics` feature.
+#[derive(Clone,Copy)]
+enum UpdateEvent {
+    FastAcquisition,
+    Spin,
+    Yield,
+}
+#[cfg(feature="metrics")]
+#[derive(Default)]
+struct UpdateCounters {
+    fast_acquisitions: AtomicUsize,
+    spins: AtomicUsize,
+    yields: AtomicUsize,
+}
+
+/// Counters of how updates of a `PairLock` acquired the inactive value,
+/// as returned by `PairLock.stats()`.
+#[cfg(feature="metrics")]
+#[derive(Clone,Copy, PartialEq,Eq, Default, Debug)]
+pub struct PairLockStats {
+    /// Updates that got the lock and the inactive value without waiting.
+    pub fast_acquisitions: usize,
+    /// Times updates have spun while waiting for reads of the inactive
+    /// value.
+    pub spins: usize,
+    /// Times updates have released the lock and yielded the thread.
+    pub yields: usize,
+}
+
+/// How often updates of a `PairLock` have had to wait,
+/// as returned by `PairLock.contention_hint()`.
+#[derive(Clone,Copy, PartialEq,Eq, Debug)]
+pub enum ContentionHint {
+    /// At most one in eight updates had to wait.
+    Low,
+    /// At most half of the updates had to wait.
+    Medium,
+    /// More than half of the updates had to wait.
+    High,
+}
+
+
 /// Error returned when a `PairLock.try_update()` fails,
 /// because it would otherwise have blocked.
-#[derive(Clone,Copy, PartialEq,Eq)]
+///
+/// More reasons might be added in the future.
+#[derive(Clone,Copy, PartialEq,Eq, Hash)]
+#[non_exhaustive]
 pub enum TryUpdateError {
     /// Was locked by another update.  
     OtherUpdate,
     /// There were unfinished reads of the inactive value.
     InactiveReads,
 }
-impl Error for TryUpdateError {
-    fn description(&self) -> &'static str {
-        match *self {
+impl TryUpdateError {
+    fn as_str(self) -> &'static str {
+        match self {
             TryUpdateError::OtherUpdate => "locked by another update",
             TryUpdateError::InactiveReads => "unfinished reads of the inactive value",
         }
     }
 }
+#[cfg(feature="std")]
+impl Error for TryUpdateError {}
 impl Display for TryUpdateError {
     fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
-        #[allow(deprecated)]
-        fmtr.write_str(self.description())
+        fmtr.write_str(self.as_str())
     }
 }
 impl Debug for TryUpdateError {
@@ -470,3 +3176,10 @@ impl Debug for TryUpdateError {
         Display::fmt(self, fmtr)
     }
 }
+#[cfg(feature="std")]
+impl From<TryUpdateError> for io::Error {
+    /// Creates an error of kind `WouldBlock`.
+    fn from(error: TryUpdateError) -> io::Error {
+        io::Error::new(io::ErrorKind::WouldBlock, error)
+    }
+}
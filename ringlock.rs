@@ -0,0 +1,257 @@
+/* Copyright 2018 Torbjørn Birch Moltu
+ *
+ * Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+ * http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+ * http://opensource.org/licenses/MIT>, at your option. This file may not be
+ * copied, modified, or distributed except according to those terms.
+ */
+
+//! `RingLock`, which generalizes `PairLock` to `N` slots.
+
+use super::{Mutex, MutexGuard, lock, try_lock, get_mut};
+use super::{AtomicUsize, fence, yield_now};
+use super::{Backoff, FixedSpins};
+use core::sync::atomic::Ordering::{SeqCst, Acquire, Release, Relaxed};
+use core::cell::UnsafeCell;
+use core::ops::{Deref,DerefMut};
+use core::fmt::{self, Debug};
+use core::mem::ManuallyDrop;
+
+/// A reader-writer lock with wait-free reads and `N` slots.
+///
+/// Like `PairLock`, reads see the active value while updates modify an
+/// inactive one before making it active. With more than two slots an update
+/// can use any inactive slot without unfinished reads, so a slow read only
+/// blocks updates once it holds up all the `N - 1` inactive slots.
+/// The cost is memory: `N` values are stored.
+///
+/// Updates pick the free slot that has been inactive the longest, so the
+/// value they see in their guard is the oldest one that can be reused.
+/// `N` must be at least 2; `RingLock<T, 2>` behaves like a `PairLock`.
+///
+/// # Examples
+/// ```
+/// # use pairlock::RingLock;
+/// let lock = RingLock::<_, 3>::with_clone(1);
+/// lock.view(|slow| {
+///     // a PairLock would block on the second update
+///     lock.set(2);
+///     lock.set(3);
+///     assert_eq!(*slow, 1);
+/// });
+/// assert_eq!(lock.read(), 3);
+/// ```
+pub struct RingLock<T, const N: usize> {
+    /// Incremented by `STEP` at the start of each view.
+    /// The bits below `STEP` store the index of the active slot.
+    reads_active: AtomicUsize,
+    /// Incremented by `STEP` at the end of each view of the slot.
+    /// Starts at the slot index so it can be compared with `reads_active`.
+    finished_reads: [AtomicUsize; N],
+    values: [UnsafeCell<T>; N],
+    /// Serializes updates.
+    state: Mutex<SlotStates<N>>,
+}
+
+struct SlotStates<const N: usize> {
+    /// The final value of `reads_active` for inactive slots.
+    /// The entry of the active slot is stale.
+    started_reads: [usize; N],
+    /// When each slot stopped being active, for picking the oldest.
+    deactivated: [u64; N],
+    /// Incremented every time a slot is made active.
+    updates: u64,
+}
+
+unsafe impl<T:Send, const N: usize> Send for RingLock<T,N> {}
+unsafe impl<T:Send+Sync, const N: usize> Sync for RingLock<T,N> {}
+
+impl<T, const N: usize> RingLock<T,N> {
+    /// Number of low bits of `reads_active` used for the slot index.
+    const SLOT_BITS: u32 = usize::BITS - (N-1).leading_zeros();
+    const STEP: usize = 1 << Self::SLOT_BITS;
+    const SLOT_MASK: usize = Self::STEP - 1;
+
+    /// Creates a new `RingLock` where the first value is active.
+    pub fn new(values: [T; N]) -> Self {
+        const { assert!(N >= 2, "a RingLock needs at least two slots") };
+        RingLock {
+            reads_active: AtomicUsize::new(0),
+            finished_reads: core::array::from_fn(AtomicUsize::new),
+            values: values.map(UnsafeCell::new),
+            state: Mutex::new(SlotStates {
+                started_reads: core::array::from_fn(|slot| slot ),
+                deactivated: [0; N],
+                updates: 0,
+            }),
+        }
+    }
+    /// Creates a new `RingLock` with clones of `init` in every slot.
+    pub fn with_clone(init: T) -> Self where T: Clone {
+        Self::new(core::array::from_fn(|_| init.clone() ))
+    }
+
+    /// View the active value of this `RingLock` inside a closure.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn view<F:FnOnce(&T)->R,R>(&self,  viewer: F) -> R {
+        // not releasing on unwind could cause use-after-free
+        struct Releaser<'a>(&'a AtomicUsize, usize);
+        impl<'a> Drop for Releaser<'a> {
+            fn drop(&mut self) {
+                self.0.fetch_add(self.1, Release);
+            }
+        }
+        let active = self.reads_active.fetch_add(Self::STEP, SeqCst) & Self::SLOT_MASK;
+        let _releaser = Releaser(&self.finished_reads[active], Self::STEP);
+        viewer(unsafe { &*self.values[active].get() })
+    }
+    /// Returns a copy of the active value.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn read(&self) -> T where T: Copy {
+        self.view(|v| *v )
+    }
+    /// Returns a clone of the active value.
+    ///
+    /// Will never block in any way, and should run in constant time.
+    pub fn get_clone(&self) -> T where T: Clone {
+        self.view(|v| v.clone() )
+    }
+
+    /// Finds the inactive slot that has been inactive the longest among
+    /// those without unfinished reads.
+    fn free_slot<'a>(&'a self,  state: MutexGuard<'a,SlotStates<N>>)
+    -> Result<RingUpdateGuard<'a,T,N>, MutexGuard<'a,SlotStates<N>>> {
+        // the lock prevents the active slot from changing
+        let active = self.reads_active.load(Relaxed) & Self::SLOT_MASK;
+        let free = (0..N)
+            .filter(|&slot| slot != active )
+            .filter(|&slot| self.finished_reads[slot].load(Relaxed) == state.started_reads[slot] )
+            .min_by_key(|&slot| state.deactivated[slot] );
+        match free {
+            Some(slot) => {
+                // synchronizes with the release in view()
+                fence(Acquire);
+                Ok(RingUpdateGuard { state: ManuallyDrop::new(state),  rl: self,  slot })
+            }
+            None => Err(state),
+        }
+    }
+    /// Locks an inactive value that has no unfinished reads, giving exclusive
+    /// access to it through a RAII guard that will make it active when
+    /// dropped.
+    ///
+    /// Blocks while another update is in progress, and spins and yields while
+    /// all inactive slots have unfinished reads.
+    /// The value in the guard is the oldest one that can be reused; use
+    /// `RingUpdateGuard::active()` to see the current one.
+    pub fn update(&self) -> RingUpdateGuard<'_,T,N> {
+        let mut backoff = FixedSpins::default();
+        let mut state = lock(&self.state);
+        loop {
+            state = match self.free_slot(state) {
+                Ok(guard) => return guard,
+                Err(state) => state,
+            };
+            if !backoff.spin() {
+                // let the readers run
+                drop(state);
+                yield_now();
+                backoff.reset();
+                state = lock(&self.state);
+            }
+        }
+    }
+    /// Attempts to lock an inactive value, returning `None` instead of
+    /// blocking if another update is in progress or all inactive slots have
+    /// unfinished reads.
+    pub fn try_update(&self) -> Option<RingUpdateGuard<'_,T,N>> {
+        self.free_slot(try_lock(&self.state)?).ok()
+    }
+    /// Stores a new value in the `RingLock`, and makes it active.
+    pub fn set(&self,  value: T) {
+        *self.update() = value;
+    }
+
+    /// Given exclusive access this method returns a mutable reference to
+    /// the active value.
+    pub fn get_mut_active(&mut self) -> &mut T {
+        let active = self.reads_active.load(Relaxed) & Self::SLOT_MASK;
+        self.values[active].get_mut()
+    }
+    /// Returns all values, starting with the active one and then from the
+    /// most recently active to the oldest.
+    pub fn into_inner(mut self) -> [T; N] {
+        let active = self.reads_active.load(Relaxed) & Self::SLOT_MASK;
+        let state = get_mut(&mut self.state);
+        let mut order: [usize; N] = core::array::from_fn(|slot| slot );
+        order.sort_by_key(|&slot| {
+            (slot != active, core::cmp::Reverse(state.deactivated[slot]))
+        });
+        let mut values = self.values.map(|value| Some(value.into_inner()) );
+        order.map(|slot| values[slot].take().unwrap() )
+    }
+}
+
+impl<T:Debug, const N: usize> Debug for RingLock<T,N> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        self.view(|v| fmtr.debug_tuple("RingLock").field(v).finish() )
+    }
+}
+
+/// RAII guard for an inactive slot of a `RingLock`, created by
+/// `RingLock.update()`.
+///
+/// Dropping it makes the value active.
+pub struct RingUpdateGuard<'a, T:'a, const N: usize> {
+    state: ManuallyDrop<MutexGuard<'a,SlotStates<N>>>,
+    rl: &'a RingLock<T,N>,
+    slot: usize,
+}
+impl<'a, T, const N: usize> RingUpdateGuard<'a,T,N> {
+    /// Returns a reference to the active value.
+    pub fn active(this: &Self) -> &T {
+        let active = this.rl.reads_active.load(Relaxed) & RingLock::<T,N>::SLOT_MASK;
+        // the active value cannot be modified while the lock is held
+        unsafe { &*this.rl.values[active].get() }
+    }
+    /// Releases the lock without making the value active.
+    pub fn cancel(this: Self) {
+        let mut this = ManuallyDrop::new(this);
+        unsafe { ManuallyDrop::drop(&mut this.state) };
+    }
+}
+impl<'a, T, const N: usize> Drop for RingUpdateGuard<'a,T,N> {
+    /// Makes the value active and releases the update lock.
+    fn drop(&mut self) {
+        let state = &mut **self.state;
+        // continue counting from the final count of the slot
+        let new_reads = state.started_reads[self.slot];
+        let old_reads = self.rl.reads_active.swap(new_reads, SeqCst);
+        let old_slot = old_reads & RingLock::<T,N>::SLOT_MASK;
+        state.started_reads[old_slot] = old_reads;
+        state.updates += 1;
+        state.deactivated[old_slot] = state.updates;
+        unsafe { ManuallyDrop::drop(&mut self.state) };
+    }
+}
+impl<'a, T, const N: usize> Deref for RingUpdateGuard<'a,T,N> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.rl.values[self.slot].get() }
+    }
+}
+impl<'a, T, const N: usize> DerefMut for RingUpdateGuard<'a,T,N> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut*self.rl.values[self.slot].get() }
+    }
+}
+impl<'a, T:Debug, const N: usize> Debug for RingUpdateGuard<'a,T,N> {
+    fn fmt(&self,  fmtr: &mut fmt::Formatter) -> fmt::Result {
+        fmtr.debug_struct("RingUpdateGuard")
+            .field("mutable", &**self)
+            .field("active", Self::active(self))
+            .finish()
+    }
+}
@@ -0,0 +1,24 @@
+// Kept out of tests.rs, as serde_json's PartialEq impls make type inference
+// of comparisons there ambiguous.
+extern crate pairlock;
+use pairlock::PairLock;
+
+#[test]
+fn serde_both() {
+    let pl = PairLock::new(vec![1], vec![2]);
+    let mut json = Vec::new();
+    pl.serialize_both(&mut serde_json::Serializer::new(&mut json)).unwrap();
+    assert_eq!(json, br#"{"active":[1],"inactive":[2]}"#);
+    assert!(pl.try_update().is_ok());
+    let mut deserializer = serde_json::Deserializer::from_slice(&json);
+    let restored = PairLock::<Vec<u8>>::deserialize_both(&mut deserializer).unwrap();
+    assert_eq!(restored.into_inner(), (vec![1], vec![2]));
+}
+
+#[test]
+fn serde_active() {
+    let pl = PairLock::new(vec![1], vec![2]);
+    assert_eq!(serde_json::to_string(&pl).unwrap(), "[1]");
+    let restored: PairLock<Vec<u8>> = serde_json::from_str("[3]").unwrap();
+    assert_eq!(restored.into_inner(), (vec![3], vec![3]));
+}
@@ -1,9 +1,19 @@
+// some imports are only used by tests of methods that require std
+#![cfg_attr(not(feature="std"), allow(unused_imports))]
+
 extern crate pairlock;
-use pairlock::{PairLock,TryUpdateError};
+use pairlock::{view_two,PairLock,PairLockBuilder,UpdateGuard,TryUpdateError,ContentionHint};
+use pairlock::{Backoff,ExponentialBackoff,Shared};
+use pairlock::{RingLock,RingUpdateGuard};
+#[cfg(feature="metrics")]
+use pairlock::PairLockStats;
 
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::ptr;
+use std::cell::Cell;
+use std::sync::{Arc, mpsc};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::{io, ptr};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[test]
 fn basic() {
@@ -40,12 +50,88 @@ fn basic_copy() {
     assert_eq!(pl.read(), "another");
 }
 
+#[cfg(feature="std")]
+#[test]
+#[cfg_attr(debug_assertions, should_panic)]
+fn view_bounded() {
+    let pl = PairLock::new(1, 0);
+    let max = Duration::from_secs(60);
+    assert_eq!(pl.view_bounded(max, |v| *v ), 1);
+    pl.view_bounded(Duration::from_millis(1), |_| thread::sleep(Duration::from_millis(20)) );
+}
+
+#[test]
+fn view_dyn() {
+    let pl = PairLock::new(1, 0);
+    let mut sum = 0;
+    let mut check = |v: &i32| assert_eq!(*v, 1);
+    let mut add = |v: &i32| sum += *v;
+    let observers: [&mut dyn FnMut(&i32); 2] = [&mut check, &mut add];
+    for observer in observers {
+        pl.view_dyn(observer);
+    }
+    assert_eq!(sum, 1);
+}
+
+#[test]
+fn peek() {
+    let pl = PairLock::new(1, 0);
+    unsafe {
+        assert_eq!(pl.peek_unsync(|v| *v ), 1);
+        pl.set(2);
+        assert_eq!(pl.peek_consistent(), 2);
+    }
+}
+
+#[test]
+fn with_capacity() {
+    let mut pl = PairLock::<Vec<u16>>::with_capacity(10);
+    assert!(pl.get_mut_active().capacity() >= 10);
+    assert!(pl.get_mut_inactive().capacity() >= 10);
+    let mut pl = PairLock::<String>::with_capacity(10);
+    assert!(pl.get_mut_active().capacity() >= 10);
+    assert!(pl.get_mut_inactive().capacity() >= 10);
+}
+
+#[test]
+fn fill_batches() {
+    let empty = vec![0u32; 4].into_boxed_slice();
+    let pl = Arc::new(PairLock::new(empty.clone(), empty));
+    let consumer = pl.clone();
+    let consumer = thread::spawn(move|| {
+        let mut last = 0;
+        while last < 1000 {
+            consumer.view(|batch| {
+                assert!(batch.iter().all(|&n| n == batch[0] ));
+                assert!(batch[0] >= last);
+                last = batch[0];
+            });
+        }
+    });
+    for n in 1..=1000 {
+        assert_eq!(pl.fill(|batch| {
+            for e in batch.iter_mut() {
+                *e = n;
+            }
+            batch.len()
+        }), 4);
+    }
+    consumer.join().unwrap();
+
+    // partial fills leave the rest of the slice as it was two fills ago
+    assert_eq!(pl.fill(|batch| {batch[0] = 0;  1}), 1);
+    pl.view(|batch| assert_eq!(&batch[..], &[0, 999, 999, 999]) );
+}
+
 #[test]
 fn basic_arc() {
     let pl = PairLock::new_arc(0);
     assert_eq!(*pl.get(), 0);
     pl.set(Arc::new(1));
     assert_eq!(*pl.get(), 1);
+    assert_eq!(pl.view_inner(|v| *v ), 1);
+    let unsized_pl = PairLock::with_clone(Arc::<[u8]>::from(&[1,2][..]));
+    assert_eq!(unsized_pl.view_inner(|v| v.len() ), 2);
 }
 
 #[test]
@@ -61,6 +147,388 @@ fn exclusive() {
     assert_eq!(pl.into_inner(), (2,1));
 }
 
+#[test]
+fn replace_both() {
+    let mut pl = PairLock::new(vec![1], vec![0]);
+    pl.set(vec![2]);
+    assert_eq!(pl.replace_both(vec![3], vec![4]), (vec![2], vec![1]));
+    assert_eq!(pl.get_clone(), vec![3]);
+    assert_eq!(pl.set(vec![5]), vec![4]);
+    assert_eq!(pl.get_clone(), vec![5]);
+    assert_eq!(pl.into_inner(), (vec![5], vec![3]));
+}
+
+#[test]
+fn slots() {
+    let pl = PairLock::new((), ());
+    let first = UpdateGuard::slot(&pl.update());
+    let second = UpdateGuard::slot(&pl.update());
+    assert_ne!(first, second);
+    let guard = pl.update();
+    assert_eq!(UpdateGuard::slot(&guard), first);
+    UpdateGuard::cancel(guard);
+    assert_eq!(UpdateGuard::slot(&pl.update()), first);
+}
+
+#[test]
+fn commit_and_view() {
+    let pl = PairLock::new(1, 0);
+    let guard = pl.update();
+    let r = UpdateGuard::commit_and_view(guard, |v| {
+        assert_eq!(pl.try_update().map(|g| *g ), Ok(1));
+        assert_eq!(pl.try_update().err(), Some(TryUpdateError::InactiveReads));
+        *v
+    });
+    assert_eq!(r, 0);
+    assert_eq!((pl.read(), pl.total_reads(), pl.total_writes()), (1, 2, 2));
+}
+
+#[test]
+fn view_generation() {
+    let pl = PairLock::new(1, 0);
+    assert_eq!(pl.view_generation(|v| *v ), (1, 0));
+    pl.set(2);
+    pl.set(3);
+    assert_eq!(pl.view_generation(|v| *v ), (3, 2));
+    UpdateGuard::cancel(pl.update());
+    assert_eq!(pl.view_generation(|v| *v ), (3, 2));
+    assert_eq!(pl.swap_take(), 3);
+    assert_eq!(pl.view_generation(|v| *v ), (0, pl.generation()));
+}
+
+#[cfg(feature="no-rotate-on-clean")]
+#[test]
+fn no_rotate_on_clean() {
+    let pl = PairLock::new_no_rotate_on_clean(1, 0);
+    drop(pl.update());
+    assert_eq!(pl.read(), 1);
+    let guard = pl.update();
+    assert_eq!((*guard, *UpdateGuard::active(&guard)), (0, 1));
+    drop(guard);
+    assert_eq!((pl.read(), pl.total_writes()), (1, 0));
+    let mut guard = pl.update();
+    UpdateGuard::both(&mut guard);
+    drop(guard);
+    assert_eq!((pl.read(), pl.total_writes()), (0, 1));
+    assert_eq!(pl.set(2), 1);
+    assert_eq!(pl.read(), 2);
+    assert_eq!(UpdateGuard::commit(pl.update()), 2);
+    assert_eq!((pl.read(), pl.total_writes()), (0, 3));
+    assert_eq!(*pl.update(), 0);
+}
+
+#[test]
+fn counters() {
+    let pl = PairLock::new(0, 0);
+    assert_eq!((pl.total_reads(), pl.total_writes()), (0, 0));
+    pl.view(|_| assert_eq!(pl.total_reads(), 0) );
+    assert_eq!(pl.total_reads(), 1);
+    pl.set(1);
+    assert_eq!(pl.read(), 1);
+    assert_eq!(pl.get_clone(), 1);
+    assert_eq!((pl.total_reads(), pl.total_writes()), (3, 1));
+    UpdateGuard::cancel(pl.update());
+    let _ = pl.try_update();
+    assert_eq!((pl.total_reads(), pl.total_writes()), (3, 2));
+    assert_eq!(pl.generation(), 2);
+    assert_eq!(pl.contention_hint(), ContentionHint::Low);
+}
+
+/// Runs with and without the `force-wraparound` feature, which changes the
+/// initial read count of the second slot.
+/// CI runs both (see `.gitlab-ci.yml`).
+#[test]
+fn start_state() {
+    let pl = PairLock::new(0, 1);
+    for i in 0..6 {
+        pl.view(|v| pl.view(|w| assert_eq!((*v, *w), (i, i)) ) );
+        // there must be no reads of the inactive value
+        let mut guard = pl.try_update().unwrap();
+        *guard = i + 1;
+    }
+    assert_eq!((pl.total_reads(), pl.total_writes()), (12, 6));
+}
+
+#[test]
+fn set_both() {
+    let pl = Arc::new(PairLock::new(vec![1], vec![0]));
+    let state = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let (pl, state) = (pl.clone(), state.clone());
+        thread::spawn(move|| pl.view(|v| {
+            state.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            assert_eq!(v, &[1]);
+            state.store(2, Ordering::SeqCst);
+        }))
+    };
+    while state.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    let mut guard = pl.update();
+    UpdateGuard::set_both(&mut guard, vec![2]);
+    drop(guard);
+    assert_eq!(state.load(Ordering::SeqCst), 2);
+    reader.join().unwrap();
+    let guard = pl.update();
+    assert_eq!((&*guard, UpdateGuard::active(&guard)), (&vec![2], &vec![2]));
+    drop(guard);
+    // cancelling discards the sync
+    let mut guard = pl.update();
+    UpdateGuard::set_both(&mut guard, vec![3]);
+    UpdateGuard::cancel(guard);
+    assert_eq!(*pl.update(), [3]);
+    assert_eq!(*pl.update(), [2]);
+}
+
+#[cfg(feature="synced")]
+#[test]
+fn new_synced() {
+    let pl = PairLock::new_synced(vec![1]);
+    assert_eq!(UpdateGuard::commit(pl.update()), [1]);
+    assert_eq!(pl.get_clone(), [1]);
+    pl.update().push(2);
+    pl.update().push(3);
+    assert_eq!(pl.get_clone(), [1, 2, 3]);
+    assert_eq!(pl.swap_take(), [1, 2, 3]);
+    assert_eq!(pl.clone().into_inner(), (vec![], vec![]));
+    UpdateGuard::cancel(pl.update());
+    assert_eq!(pl.into_inner(), (vec![], vec![]));
+}
+
+#[test]
+fn swap_take() {
+    let pl = Arc::new(PairLock::new(vec![1], vec![0]));
+    let state = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let (pl, state) = (pl.clone(), state.clone());
+        thread::spawn(move|| pl.view(|v| {
+            state.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            assert_eq!(v, &[1]);
+            state.store(2, Ordering::SeqCst);
+        }))
+    };
+    while state.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    assert_eq!(pl.swap_take(), [1]);
+    assert_eq!(state.load(Ordering::SeqCst), 2);
+    reader.join().unwrap();
+    pl.update_with_lock(|new, old| {
+        assert!(new.is_empty() && old.is_empty());
+        new.push(2);
+    });
+    assert_eq!(pl.swap_take(), [2]);
+    assert_eq!(pl.total_writes(), 3);
+}
+
+#[test]
+fn get_mut_inner() {
+    let mut aliased = PairLock::new_arc(vec![1]);
+    assert_eq!(aliased.get_mut_inner(), None);
+    let mut pl = PairLock::new(Arc::new(vec![1]), Arc::new(vec![0]));
+    pl.get_mut_inner().unwrap().push(2);
+    let held = pl.get();
+    assert_eq!(pl.get_mut_inner(), None);
+    drop(held);
+    assert_eq!(pl.get_mut_inner(), Some(&mut vec![1, 2]));
+}
+
+#[cfg(feature="synced")]
+#[test]
+fn push_synced() {
+    let pl = PairLock::new_synced(vec![1]);
+    pl.push(2);
+    assert_eq!(pl.into_inner(), (vec![1, 2], vec![1, 2]));
+}
+
+#[test]
+fn commit_returns_previous() {
+    let pl = Arc::new(PairLock::new(vec![1], vec![0]));
+    let state = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let (pl, state) = (pl.clone(), state.clone());
+        thread::spawn(move|| pl.view(|v| {
+            state.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            assert_eq!(v, &[1]);
+            state.store(2, Ordering::SeqCst);
+        }))
+    };
+    while state.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    let mut guard = pl.update();
+    guard.push(2);
+    assert_eq!(UpdateGuard::commit(guard), [1]);
+    assert_eq!(state.load(Ordering::SeqCst), 2);
+    reader.join().unwrap();
+    assert_eq!((pl.get_clone(), pl.update().clone()), (vec![0, 2], vec![0, 2]));
+}
+
+#[test]
+fn push_unequal() {
+    let pl = PairLock::new(vec![1], Vec::new());
+    pl.push(2);
+    assert_eq!(pl.get_clone(), [1, 2]);
+    pl.push(3);
+    assert_eq!(pl.into_inner(), (vec![1, 2, 3], vec![1, 2, 3]));
+}
+
+#[test]
+fn weak_get_or_init() {
+    use std::sync::Weak;
+    let cache = Arc::new(PairLock::new(Weak::new(), Weak::new()));
+    let inits = Arc::new(AtomicUsize::new(0));
+    let threads = (0..4).map(|_| {
+        let (cache, inits) = (cache.clone(), inits.clone());
+        thread::spawn(move|| cache.get_or_init(|| {
+            inits.fetch_add(1, Ordering::SeqCst);
+            Arc::new(1)
+        }))
+    }).collect::<Vec<_>>();
+    let arcs = threads.into_iter().map(|t| t.join().unwrap() ).collect::<Vec<_>>();
+    assert_eq!(inits.load(Ordering::SeqCst), 1);
+    assert!(arcs.iter().all(|arc| Arc::ptr_eq(arc, &arcs[0]) ));
+    drop(arcs);
+    assert_eq!(*cache.get_or_init(|| Arc::new(2) ), 2);
+}
+
+#[test]
+fn arc_conversions() {
+    let pl = PairLock::new(vec![1], vec![0]).into_arc();
+    assert_eq!(*pl.get(), [1]);
+    let held = pl.get();
+    let pl = pl.try_into_unshared().unwrap_err();
+    drop(held);
+    let pl = pl.try_into_unshared().unwrap();
+    assert_eq!(pl.into_inner(), (vec![1], vec![0]));
+    let pl = PairLock::new_arc(0);
+    assert!(pl.try_into_unshared().is_err());
+    // still synced
+    #[cfg(feature="synced")] {
+        let pl = PairLock::new_synced(1).into_arc();
+        pl.set(Arc::new(2));
+        let (active, inactive) = pl.into_inner();
+        assert!(Arc::ptr_eq(&active, &inactive));
+    }
+}
+
+#[test]
+fn reset() {
+    let mut pl = PairLock::new(vec![0], vec![1]);
+    for i in 2..5 {
+        pl.set(vec![i]);
+    }
+    pl.view(|_| pl.view(|_| {} ) );
+    pl.reset(vec![9]);
+    let fresh = PairLock::with_clone(vec![9]);
+    for pl in [pl, fresh] {
+        assert_eq!((pl.total_reads(), pl.total_writes()), (0, 0));
+        assert_eq!(pl.get_clone(), vec![9]);
+        assert_eq!(pl.set(vec![10]), vec![9]);
+        assert!(pl.try_update().is_ok());
+        assert_eq!(pl.into_inner(), (vec![9], vec![10]));
+    }
+}
+
+#[test]
+fn view_cached_after_reset() {
+    let mut pl = PairLock::with_clone(1);
+    let mut cache = None;
+    assert_eq!(pl.view_cached(&mut cache, |v| *v ), 1);
+    let (_, generation) = pl.view_generation(|_| {} );
+    pl.reset(5);
+    assert_eq!(pl.view_cached(&mut cache, |v| *v ), 5);
+    assert!(pl.view_generation(|_| {} ).1 > generation);
+    assert!(pl.generation() > generation);
+    // the generation keeps increasing while the write count starts over
+    let generation = pl.generation();
+    pl.set(6);
+    pl.reset(7);
+    assert_eq!(pl.total_writes(), 0);
+    assert!(pl.generation() > generation + 1);
+    pl.set(8);
+    assert_eq!(pl.total_writes(), 1);
+}
+
+#[cfg(feature="std")]
+#[test]
+fn quiesce() {
+    let pl = PairLock::new((), ());
+    pl.quiesce();
+    pl.view(|_| {
+        assert!(!pl.quiesce_timeout(Duration::from_millis(1)));
+        pl.set(());
+        // only reads of the new value are waited for
+        assert!(pl.quiesce_timeout(Duration::from_millis(1)));
+        pl.view(|_| assert!(!pl.quiesce_timeout(Duration::from_millis(1))) );
+    });
+    pl.quiesce();
+    assert!(pl.quiesce_timeout(Duration::from_millis(1)));
+    // the deadline overflows
+    assert!(pl.quiesce_timeout(Duration::MAX));
+}
+
+#[cfg(feature="std")]
+#[test]
+fn quiesce_timeout_during_update() {
+    let pl = &PairLock::new(1, 0);
+    let (started, wait) = mpsc::channel();
+    let (release, released) = mpsc::channel::<()>();
+    thread::scope(|s| {
+        s.spawn(move|| pl.view(|_| {
+            started.send(()).unwrap();
+            released.recv().unwrap();
+        }));
+        wait.recv().unwrap();
+        let quiescer = s.spawn(|| {
+            let start = Instant::now();
+            (pl.quiesce_timeout(Duration::from_millis(100)), start.elapsed())
+        });
+        thread::sleep(Duration::from_millis(20));
+        // replace the value being waited for, and then hold the lock while
+        // waiting for the read of it
+        pl.set(2);
+        let updater = s.spawn(|| pl.set(3) );
+        let (quiesced, waited) = quiescer.join().unwrap();
+        assert!(!quiesced);
+        assert!(waited < Duration::from_secs(1), "waited {:?}", waited);
+        release.send(()).unwrap();
+        updater.join().unwrap();
+    });
+    assert_eq!(pl.read(), 3);
+}
+
+#[cfg(feature="relaxed")]
+#[test]
+fn relaxed_orderings() {
+    for pl in [PairLock::new([0; 8], [0; 8]), PairLock::new_relaxed([0; 8], [0; 8])] {
+        let pl = Arc::new(pl);
+        let readers = (0..2).map(|_| {
+            let pl = pl.clone();
+            thread::spawn(move|| {
+                let mut last = 0;
+                while last < 1000 {
+                    pl.view(|v| {
+                        assert!(v.iter().all(|&n| n == v[0] ));
+                        assert!(v[0] >= last);
+                        last = v[0];
+                    });
+                }
+            })
+        }).collect::<Vec<_>>();
+        for i in 1..=1000 {
+            pl.set([i; 8]);
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(pl.clone().read(), [1000; 8]);
+    }
+}
+
 #[test]
 fn singlethreaded_locking() {
     let r = PairLock::new((),());
@@ -76,6 +544,54 @@ fn singlethreaded_locking() {
     });
 }
 
+#[cfg(feature="std")]
+#[test]
+fn io_error() {
+    let pl = PairLock::new((), ());
+    let _guard = pl.update();
+    let error = pl.try_update().map_err(io::Error::from).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(error.to_string(), TryUpdateError::OtherUpdate.to_string());
+}
+
+#[test]
+fn nested_views_dont_deadlock() {
+    let (a, b, c) = (PairLock::new(1, 0), PairLock::new(2, 0), PairLock::new(3, 0));
+    let d = PairLock::new(4, 0);
+    let sum = a.view(|&a1| b.view(|&b1| c.view(|&c1| a.view(|&a2| {
+        d.set(a1 + b1 + c1 + a2);
+        c.view(|&c2| b.view(|&b2| a2 + b2 + c2 ))
+    }))));
+    assert_eq!((sum, d.read()), (6, 7));
+
+    // readers in different orders while another thread updates
+    let locks = Arc::new([a, b, c, d]);
+    let readers = (0..4).map(|offset| {
+        let locks = locks.clone();
+        thread::spawn(move|| {
+            for _ in 0..1000 {
+                let [first, second, third] = [offset, offset+3, offset+1].map(|i| &locks[i%4] );
+                first.view(|_| second.view(|_| third.view(|_| first.view(|_| {} ) )));
+            }
+        })
+    }).collect::<Vec<_>>();
+    for i in 0..1000 {
+        locks[i%4].set(i);
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+}
+
+#[test]
+fn send_sync() {
+    fn assert_send<T:Send>() {}
+    fn assert_sync<T:Sync>() {}
+    assert_send::<PairLock<Cell<u32>>>();
+    assert_send::<PairLock<Vec<u32>>>();
+    assert_sync::<PairLock<Vec<u32>>>();
+}
+
 #[test]
 fn drop_runs() {
     static DROPS: AtomicUsize = AtomicUsize::new(0);
@@ -122,11 +638,68 @@ fn debug_fmt() {
 }
 
 
+#[test]
+fn from() {
+    assert_eq!(PairLock::from(1).into_inner(), (1, 1));
+    assert_eq!(PairLock::from((1, 2)).into_inner(), (1, 2));
+}
+
+#[test]
+fn eq() {
+    let a = PairLock::new(1, 2);
+    let b = PairLock::new(1, 3);
+    assert_eq!(a, b);
+    assert_eq!(a, a);
+    b.set(2);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    fn hash<T:Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+    let pl = PairLock::new("foo".to_string(), "bar".to_string());
+    assert_eq!(hash(&pl), hash(&"foo".to_string()));
+}
+
+
+#[test]
+fn display_fmt() {
+    let pl = PairLock::new("foo".to_string(), "bar".to_string());
+    assert_eq!(format!("{}", pl), "foo");
+    assert_eq!(format!("{:>4}", pl), " foo");
+}
+
 #[test]
 fn default() {
     assert_eq!(PairLock::<bool>::default().read(), bool::default());
 }
 
+#[test]
+fn arc_swap_names() {
+    let pl = PairLock::new_arc(1);
+    pl.store(Arc::new(2));
+    assert_eq!((*pl.load(), *pl.load_full()), (2, 2));
+}
+
+#[test]
+fn shared_trait_objects() {
+    let pl = PairLock::new_arc(String::from("pair"));
+    let rl = RingLock::<_,2>::with_clone(Arc::new(String::from("ring")));
+    let shared: [&dyn Shared<String>; 2] = [&pl, &rl];
+    for s in &shared {
+        let appended = format!("{}!", s.load());
+        s.store(Arc::new(appended));
+    }
+    assert_eq!(*pl.get(), "pair!");
+    assert_eq!(*rl.get_clone(), "ring!");
+}
+
 #[test]
 fn pointers() {
     let t1 = Arc::new(true);
@@ -141,4 +714,449 @@ fn pointers() {
     assert!(ptr::eq(&*c.get(), t1_ptr));
     c.set(t2);
     assert!(ptr::eq(&*c.get(), t2_ptr));
+    assert!(c.with_ptr(|p| ptr::eq(p, t2_ptr) ));
+    assert_eq!(Arc::strong_count(&c.get()), 2);
+}
+
+#[test]
+fn on_commit() {
+    let pl = PairLock::new(vec![1], vec![0]);
+    let sum = Arc::new(AtomicUsize::new(0));
+    let sum_ = sum.clone();
+    pl.on_commit(move |v| {sum_.fetch_add(v.iter().sum(), Ordering::SeqCst);} );
+    let len = pl.project(|v| v.len() );
+    assert_eq!(len.read(), 1);
+    pl.set(vec![2, 3]);
+    assert_eq!((len.read(), sum.load(Ordering::SeqCst)), (2, 5));
+    UpdateGuard::cancel(pl.update());
+    assert_eq!((len.read(), sum.load(Ordering::SeqCst)), (2, 5));
+    drop(pl.update());
+    assert_eq!((len.read(), sum.load(Ordering::SeqCst)), (1, 6));
+    drop(len);
+    pl.set(vec![]);
+    assert_eq!(sum.load(Ordering::SeqCst), 6);
+}
+
+#[cfg(feature="std")]
+#[test]
+fn forward_changes() {
+    let pl = PairLock::new(String::new(), String::new());
+    let (tx, rx) = mpsc::channel();
+    pl.forward_changes(tx);
+    for s in ["a", "b", "c"] {
+        pl.set(s.to_string());
+    }
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+    drop(rx);
+    pl.set("d".to_string());
+}
+
+#[test]
+fn with_update() {
+    let pl = PairLock::with_clone(1);
+    let active = pl.with_update(|guard| {
+        assert!(pl.try_update().is_err());
+        **guard = UpdateGuard::active(guard) + 1;
+        **guard *= 10;
+        *UpdateGuard::active(guard)
+    });
+    assert_eq!((active, pl.read()), (1, 20));
+}
+
+#[test]
+fn view_two_panic() {
+    let (a, b) = (PairLock::with_clone(1), PairLock::with_clone('b'));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        view_two(&a, &b, |_, _| panic!("viewer failed") )
+    }));
+    assert!(result.is_err());
+    // both reads are finished, so neither lock has reads of its inactive value
+    for _ in 0..2 {
+        a.try_update().unwrap();
+        b.try_update().unwrap();
+    }
+    assert_eq!(view_two(&a, &b, |&a, &b| (a, b) ), (1, 'b'));
+}
+
+#[test]
+fn custom_backoff_helpers() {
+    let backoff = ExponentialBackoff::default();
+    let a = PairLock::with_backoff(1, 0, backoff);
+    let b = PairLock::<Vec<char>,_>::with_capacity_and_backoff(4, backoff);
+    b.push('b');
+    assert_eq!(view_two(&a, &b, |&a, b| (a, b.clone()) ), (1, vec!['b']));
+    let zipped: PairLock<_,ExponentialBackoff> = PairLock::zip(a, b);
+    assert_eq!(zipped.get_clone(), (1, vec!['b']));
+    let s = PairLock::<String,_>::with_capacity_and_backoff(10, backoff);
+    s.set(String::from("s"));
+    assert!(s.into_inner().1.capacity() >= 10);
+}
+
+#[test]
+fn update_with_lock_panic() {
+    let pl = PairLock::new(vec![1], vec![0]);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        pl.update_with_lock(|new, old| {
+            new.clone_from(old);
+            new.push(2);
+            panic!("mutation failed");
+        })
+    }));
+    assert!(result.is_err());
+    // not poisoned, and the value was committed like when dropping a guard
+    assert_eq!(pl.get_clone(), [1, 2]);
+    assert_eq!(pl.update_with_lock(|new, old| {new.clone_from(old);  new.len()}), 2);
+}
+
+#[test]
+fn prepare_panic() {
+    let pl = PairLock::new(1, 0);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        pl.prepare(|next, _| {
+            *next = 2;
+            panic!("in prepare");
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(pl.read(), 1);
+    assert_eq!(*pl.update(), 2);
+}
+
+#[derive(Debug)]
+struct CloneFails(u32);
+impl Clone for CloneFails {
+    fn clone(&self) -> Self {
+        assert_ne!(self.0, 0, "clone failed");
+        CloneFails(self.0)
+    }
+}
+
+#[test]
+fn commit_panic() {
+    let pl = PairLock::new(1, 0);
+    let fail = Arc::new(AtomicBool::new(true));
+    let hook_fail = fail.clone();
+    pl.on_commit(move |_| assert!(!hook_fail.swap(false, Ordering::SeqCst), "hook failed") );
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pl.set(2) ));
+    assert!(result.is_err());
+    // the value was made active before the hook ran, and the lock released
+    assert_eq!(pl.read(), 2);
+    pl.try_update().unwrap();
+    fail.store(true, Ordering::SeqCst);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        UpdateGuard::commit_and_view(pl.update(), |v| *v )
+    }));
+    assert!(result.is_err());
+    // the read started by commit_and_view() was finished
+    for _ in 0..2 {
+        pl.try_update().unwrap();
+    }
+
+    let both = PairLock::new(CloneFails(1), CloneFails(1));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        UpdateGuard::set_both(&mut both.update(), CloneFails(0))
+    }));
+    assert!(result.is_err());
+    assert_eq!(both.view(|v| v.0 ), 0);
+    *both.try_update().unwrap() = CloneFails(2);
+    assert_eq!(both.view(|v| v.0 ), 2);
+
+    #[cfg(feature="synced")] {
+        let synced = PairLock::new_synced(CloneFails(1));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            synced.set(CloneFails(0))
+        }));
+        assert!(result.is_err());
+        assert_eq!(synced.view(|v| v.0 ), 0);
+        *synced.try_update().unwrap() = CloneFails(2);
+        assert_eq!(synced.view(|v| v.0 ), 2);
+    }
+}
+
+#[test]
+fn swap_with() {
+    let a = Arc::new(PairLock::new(vec![1], Vec::new()));
+    let b = Arc::new(PairLock::new(vec![2], Vec::new()));
+    a.swap_with(&a);
+    assert_eq!(a.get_clone(), [1]);
+    // opposite order must not deadlock
+    let swapper = {
+        let (a, b) = (a.clone(), b.clone());
+        thread::spawn(move|| for _ in 0..1000 {b.swap_with(&a)} )
+    };
+    for _ in 0..999 {
+        a.swap_with(&b);
+    }
+    swapper.join().unwrap();
+    assert_eq!((a.get_clone(), b.get_clone()), (vec![2], vec![1]));
+}
+
+#[cfg(feature="std")]
+#[test]
+fn update_timeout() {
+    let pl = PairLock::new(1, 0);
+    pl.view(|_| {
+        pl.set(2);
+        let started = Instant::now();
+        let result = pl.update_timeout(Duration::from_millis(10));
+        assert_eq!(result.unwrap_err(), TryUpdateError::InactiveReads);
+        assert!(started.elapsed() >= Duration::from_millis(10));
+    });
+    *pl.update_timeout(Duration::from_millis(10)).unwrap() = 3;
+    assert_eq!(pl.read(), 3);
+    // the deadline overflows
+    *pl.update_timeout(Duration::MAX).unwrap() = 4;
+    assert_eq!(pl.read(), 4);
+}
+
+#[cfg(feature="park")]
+#[test]
+fn update_parks_for_slow_read() {
+    let pl = Arc::new(PairLock::new(1, 0));
+    let state = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let (pl, state) = (pl.clone(), state.clone());
+        thread::spawn(move|| pl.view(|_| {
+            state.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            state.store(2, Ordering::SeqCst);
+        }))
+    };
+    while state.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    pl.set(2);
+    pl.set(3);
+    assert_eq!(state.load(Ordering::SeqCst), 2);
+    reader.join().unwrap();
+    assert_eq!(pl.read(), 3);
+}
+
+#[cfg(feature="std")]
+#[test]
+fn builder() {
+    for spins in [0, 1, 100] {
+        let pl = PairLockBuilder::new().spins(spins).build(1, 0);
+        pl.view(|_| {
+            pl.set(2);
+            let result = pl.update_timeout(Duration::from_millis(1));
+            assert_eq!(result.unwrap_err(), TryUpdateError::InactiveReads);
+        });
+        pl.set(3);
+        assert_eq!(pl.clone().read(), 3);
+    }
+}
+
+#[cfg(feature="std")]
+#[test]
+fn backoff() {
+    #[derive(Clone)]
+    struct Counting(Arc<AtomicUsize>);
+    impl Backoff for Counting {
+        fn reset(&mut self) {}
+        fn spin(&mut self) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst) % 3 != 2
+        }
+    }
+    let spins = Arc::new(AtomicUsize::new(0));
+    let pl = PairLockBuilder::new().backoff(Counting(spins.clone())).build(1, 0);
+    pl.set(2);
+    assert_eq!(spins.load(Ordering::SeqCst), 0);
+    pl.view(|_| {
+        pl.set(3);
+        assert!(pl.update_timeout(Duration::from_millis(1)).is_err());
+    });
+    assert_ne!(spins.load(Ordering::SeqCst), 0);
+
+    let pl = PairLockBuilder::new().backoff(ExponentialBackoff::default()).build(1, 0);
+    pl.view(|_| {
+        assert!(!pl.quiesce_timeout(Duration::from_millis(1)));
+        pl.set(2);
+        assert!(pl.update_timeout(Duration::from_millis(1)).is_err());
+    });
+    assert_eq!(pl.swap_take(), 2);
+}
+
+#[cfg(feature="async")]
+#[test]
+fn update_async() {
+    use futures::executor::block_on;
+    let pl = Arc::new(PairLock::new(1, 0));
+    *block_on(pl.update_async()) = 2;
+    assert_eq!(pl.read(), 2);
+
+    // waiting for another update
+    let mut guard = pl.update();
+    let waiter = {
+        let pl = pl.clone();
+        thread::spawn(move|| *block_on(pl.update_async()) = 4 )
+    };
+    thread::sleep(Duration::from_millis(10));
+    *guard = 3;
+    drop(guard);
+    waiter.join().unwrap();
+    assert_eq!(pl.read(), 4);
+
+    // waiting for reads of the inactive value
+    let state = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let (pl, state) = (pl.clone(), state.clone());
+        thread::spawn(move|| pl.view(|_| {
+            state.store(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            state.store(2, Ordering::SeqCst);
+        }))
+    };
+    while state.load(Ordering::SeqCst) == 0 {
+        thread::yield_now();
+    }
+    pl.set(5);
+    *block_on(pl.update_async()) = 6;
+    assert_eq!(state.load(Ordering::SeqCst), 2);
+    reader.join().unwrap();
+    assert_eq!(pl.read(), 6);
+}
+
+#[cfg(feature="async")]
+#[test]
+fn watch() {
+    use futures::executor::block_on;
+    let pl = Arc::new(PairLock::new(1, 0));
+    let (tx, rx) = mpsc::channel();
+    let watcher = {
+        let pl = pl.clone();
+        thread::spawn(move|| block_on(async {
+            let mut watcher = pl.watch();
+            loop {
+                let value = watcher.recv().await;
+                tx.send(value).unwrap();
+                if value == 3 {
+                    break;
+                }
+            }
+        }))
+    };
+    assert_eq!(rx.recv(), Ok(1));
+    // reads and cancelled updates don't count as changes
+    pl.view(|_| {} );
+    UpdateGuard::cancel(pl.update());
+    thread::sleep(Duration::from_millis(10));
+    assert!(rx.try_recv().is_err());
+    pl.set(2);
+    assert_eq!(rx.recv(), Ok(2));
+    pl.set(3);
+    watcher.join().unwrap();
+    assert_eq!(rx.recv(), Ok(3));
+}
+
+#[cfg(feature="read-guard")]
+#[test]
+fn guard_read() {
+    fn first(pl: &PairLock<Vec<u32>>) -> pairlock::ReadGuard<'_,Vec<u32>> {
+        pl.guard_read()
+    }
+    let pl = PairLock::new(vec![1], vec![0]);
+    let guard = first(&pl);
+    assert_eq!(pl.try_set(vec![2]), Ok(vec![0]));
+    assert_eq!(pl.try_set(vec![3]).unwrap_err().1, TryUpdateError::InactiveReads);
+    thread::scope(|s| s.spawn(|| assert_eq!(*guard, [1]) ).join().unwrap() );
+    drop(guard);
+    assert_eq!(pl.try_set(vec![3]), Ok(vec![1]));
+    std::mem::forget(pl.guard_read());
+    assert!(pl.try_set(vec![4]).is_ok());
+    assert!(pl.try_set(vec![5]).is_err());
+}
+
+#[test]
+fn set_contended() {
+    let pl = PairLock::new(vec![1], vec![0]);
+    for i in 2..5 {
+        assert_eq!(pl.set_contended(vec![i]), (vec![i-2], 0));
+    }
+}
+
+#[test]
+fn contention_hint() {
+    let pl = Arc::new(PairLock::new(0, 0));
+    assert_eq!(pl.contention_hint(), ContentionHint::Low);
+    // a read of the inactive value makes the second update wait
+    let contend = |commit: bool| {
+        let state = Arc::new(AtomicUsize::new(0));
+        let reader = {
+            let (pl, state) = (pl.clone(), state.clone());
+            thread::spawn(move|| pl.view(|_| {
+                state.store(1, Ordering::SeqCst);
+                while state.load(Ordering::SeqCst) != 2 {
+                    thread::yield_now();
+                }
+            }))
+        };
+        while state.load(Ordering::SeqCst) != 1 {
+            thread::yield_now();
+        }
+        drop(pl.update());
+        let releaser = thread::spawn(move|| {
+            thread::sleep(Duration::from_millis(10));
+            state.store(2, Ordering::SeqCst);
+        });
+        if commit {
+            assert_ne!(pl.set_contended(1).1, 0);
+        } else {
+            UpdateGuard::cancel(pl.update());
+        }
+        reader.join().unwrap();
+        releaser.join().unwrap();
+    };
+    for _ in 0..4 {
+        contend(true);
+    }
+    assert_eq!(pl.contention_hint(), ContentionHint::Medium);
+    // cancelled updates count neither as writes nor as contended
+    for _ in 0..4 {
+        contend(false);
+    }
+    assert_eq!(pl.total_writes(), 12);
+    assert_eq!(pl.contention_hint(), ContentionHint::Medium);
+}
+
+#[cfg(feature="metrics")]
+#[test]
+fn stats() {
+    let pl = PairLock::new(0, 0);
+    pl.set(1);
+    assert_eq!(pl.stats(), PairLockStats { fast_acquisitions: 1,  spins: 0,  yields: 0 });
+    let (started, wait) = mpsc::channel();
+    thread::scope(|s| {
+        s.spawn(|| pl.view(|_| {
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }));
+        wait.recv().unwrap();
+        // the read becomes a read of the inactive value
+        pl.set(2);
+        pl.set(3);
+    });
+    let stats = pl.stats();
+    assert_eq!(stats.fast_acquisitions, 2);
+    assert_ne!(stats.spins, 0);
+}
+
+#[test]
+fn ring_lock() {
+    let rl = RingLock::new([1, 0, 0]);
+    rl.view(|first| {
+        rl.set(2);
+        rl.view(|second| {
+            assert_eq!(*rl.update(), 0);
+            assert!(rl.try_update().is_none());
+            assert_eq!((*first, *second), (1, 2));
+        });
+        // the slot that was active during the nested view is now the oldest
+        let guard = rl.update();
+        assert_eq!((*guard, *RingUpdateGuard::active(&guard)), (2, 0));
+        RingUpdateGuard::cancel(guard);
+    });
+    assert_eq!(rl.read(), 0);
+    rl.set(3);
+    assert_eq!(rl.into_inner(), [3, 0, 2]);
 }